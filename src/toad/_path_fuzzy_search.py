@@ -1,48 +1,32 @@
-from functools import lru_cache
-from typing import Sequence
-import re
-
-
-from toad.fuzzy import FuzzySearch
+from toad.fuzzy import FuzzyConfig, FuzzySearch
 
 
 class PathFuzzySearch(FuzzySearch):
-    @classmethod
-    @lru_cache(maxsize=1024)
-    def get_first_letters(cls, candidate: str) -> frozenset[int]:
-        return frozenset(
-            {
-                0,
-                *[match.start() + 1 for match in re.finditer(r"/", candidate)],
-            }
-        )
-
-    def score(self, candidate: str, positions: Sequence[int]) -> float:
-        """Score a search.
+    """A fuzzy search with path mode enabled."""
+
+    def __init__(
+        self,
+        case_sensitive: bool = False,
+        *,
+        cache_size: int = 1024 * 4,
+        depth_penalty: float = 0.0,
+        config: FuzzyConfig | None = None,
+    ) -> None:
+        """Initialize path fuzzy search.
 
         Args:
-            search: Search object.
-
-        Returns:
-            Score.
+            case_sensitive: Is the match case sensitive?
+            cache_size: Number of queries to cache.
+            depth_penalty: Reduce the score by this factor for each `/` preceding
+                the first matched character, so shallower matches rank higher.
+            config: Full configuration, which replaces the other arguments if given.
         """
-        first_letters = self.get_first_letters(candidate)
-        # This is a heuristic, and can be tweaked for better results
-        # Boost first letter matches
-        offset_count = len(positions)
-        score: float = offset_count + len(first_letters.intersection(positions))
-
-        groups = 1
-        last_offset, *offsets = positions
-        for offset in offsets:
-            if offset != last_offset + 1:
-                groups += 1
-            last_offset = offset
-
-        # Boost to favor less groups
-        normalized_groups = (offset_count - (groups - 1)) / offset_count
-        score *= 1 + (normalized_groups * normalized_groups)
-
-        if positions[0] > candidate.rfind("/"):
-            score *= 2
-        return score
+        if config is None:
+            config = FuzzyConfig(
+                case_sensitive=case_sensitive,
+                path_mode=True,
+                cache_size=cache_size,
+                depth_penalty=depth_penalty,
+            )
+        super().__init__(config=config)
+        self.config.path_mode = True
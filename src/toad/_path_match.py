@@ -88,8 +88,10 @@ class PathFuzzySearch:
 
         score = self.score
 
+        query_length = len(query)
         for offset, letter in enumerate(query):
-            last_index = len(candidate) - offset
+            # Leave room for the remaining letters of the query
+            last_index = len(candidate) - (query_length - offset - 1)
             positions: list[int] = []
             letter_positions.append(positions)
             index = position
@@ -104,7 +106,6 @@ class PathFuzzySearch:
             position = positions[0] + 1
 
         possible_offsets: list[list[int]] = []
-        query_length = len(query)
 
         def get_offsets(offsets: list[int], positions_index: int) -> None:
             """Recursively match offsets.
@@ -2,6 +2,8 @@ from __future__ import annotations
 
 import asyncio
 from itertools import filterfalse
+import logging
+from math import isfinite
 from typing import Callable
 from time import time
 from os import PathLike
@@ -11,6 +13,8 @@ from textual._partition import partition
 
 from toad.path_filter import PathFilter
 
+log = logging.getLogger("directory")
+
 
 class ScanJob:
     """A single directory scanning job."""
@@ -22,12 +26,16 @@ class ScanJob:
         results: list[Path],
         path_filter: PathFilter | None = None,
         add_directories=False,
+        seen_paths: set[Path] | None = None,
+        max_results: int | None = None,
     ) -> None:
         self.queue = queue
         self.results = results
         self.name = name
         self.path_filter = path_filter
         self.add_directories = add_directories
+        self.seen_paths = seen_paths
+        self.max_results = max_results
 
     def start(self) -> None:
         self._task = asyncio.create_task(self.run())
@@ -44,16 +52,69 @@ class ScanJob:
             paths, dir_paths = await asyncio.to_thread(
                 self._scan_directory, scan_path, self.path_filter
             )
+            if (seen_paths := self.seen_paths) is not None:
+                canonical_paths, canonical_dir_paths = await asyncio.to_thread(
+                    lambda: (self._canonicalize(paths), self._canonicalize(dir_paths))
+                )
+                paths = self._deduplicate(paths, canonical_paths, seen_paths)
+                dir_paths = self._deduplicate(
+                    dir_paths, canonical_dir_paths, seen_paths
+                )
             if add_directories:
                 results.extend(dir_paths)
             results.extend(paths)
+            if self.max_results is not None and len(results) >= self.max_results:
+                # Collected enough paths; stop all jobs
+                queue.shutdown(immediate=True)
+                queue.task_done()
+                break
             try:
                 for path in dir_paths:
                     await queue.put(path)
             except asyncio.QueueShutDown:
+                queue.task_done()
                 break
             queue.task_done()
 
+    @classmethod
+    def _canonicalize(cls, paths: list[Path]) -> list[Path]:
+        """Resolve symlinks in paths (done in a thread).
+
+        Args:
+            paths: Paths to resolve.
+
+        Returns:
+            A list of canonical paths, in the same order.
+        """
+        canonical_paths: list[Path] = []
+        for path in paths:
+            try:
+                canonical_paths.append(path.resolve())
+            except (OSError, RuntimeError):
+                canonical_paths.append(path)
+        return canonical_paths
+
+    @classmethod
+    def _deduplicate(
+        cls, paths: list[Path], canonical_paths: list[Path], seen_paths: set[Path]
+    ) -> list[Path]:
+        """Remove paths which resolve to a previously seen path.
+
+        Args:
+            paths: Paths to deduplicate.
+            canonical_paths: Canonical versions of `paths`.
+            seen_paths: Canonical paths seen so far (will be updated).
+
+        Returns:
+            Paths which weren't seen before.
+        """
+        unique_paths: list[Path] = []
+        for path, canonical_path in zip(paths, canonical_paths):
+            if canonical_path not in seen_paths:
+                seen_paths.add(canonical_path)
+                unique_paths.append(path)
+        return unique_paths
+
     def _scan_directory(
         self, root: Path, path_filter: PathFilter | None = None
     ) -> tuple[list[Path], list[Path]]:
@@ -88,21 +149,49 @@ async def scan(
     path_filter: PathFilter | None = None,
     add_directories: bool = False,
     max_duration: float | None = 5.0,
+    canonicalize_dedup: bool = False,
+    sort: bool = False,
+    max_results: int | None = None,
 ) -> list[Path]:
     """Scan a directory for paths.
 
     Args:
         root: Root directory to scan.
-        max_simultaneous: Maximum number of scan jobs.
+        max_simultaneous: Maximum number of scan jobs. If less than 1, a single job
+            scans serially.
         path_filter: Path filter object.
         add_directories: Also collect directories?
         max_duration: Maximum time in seconds to scan for, or `None` for no maximum.
+        canonicalize_dedup: Resolve symlinks and drop paths that point to an
+            already collected file or directory. Requires an extra syscall per path.
+        sort: Sort the results? Otherwise paths are returned in the order they were scanned.
+        max_results: Stop scanning after collecting this many paths, or `None` for no limit.
+            Since directories are scanned concurrently, which paths are returned may vary.
 
     Returns:
         A list of Paths.
+
+    Raises:
+        ValueError: If `max_duration` is negative or not finite, or `max_results` is negative.
     """
+    if max_duration is not None and not (
+        isfinite(max_duration) and max_duration >= 0
+    ):
+        raise ValueError(
+            f"max_duration must be a non-negative number of seconds; got {max_duration!r}"
+        )
+    if max_results is not None and max_results < 0:
+        raise ValueError(f"max_results must not be negative; got {max_results!r}")
+    if max_simultaneous < 1:
+        log.warning(
+            f"Invalid max_simultaneous ({max_simultaneous!r}), scanning serially"
+        )
+        max_simultaneous = 1
     queue: asyncio.Queue[Path] = asyncio.Queue()
     results: list[Path] = []
+    seen_paths: set[Path] | None = (
+        {ScanJob._canonicalize([root])[0]} if canonicalize_dedup else None
+    )
     jobs = [
         ScanJob(
             f"scan-job #{index}",
@@ -110,6 +199,8 @@ async def scan(
             results,
             path_filter=path_filter,
             add_directories=add_directories,
+            seen_paths=seen_paths,
+            max_results=max_results,
         )
         for index in range(max_simultaneous)
     ]
@@ -128,7 +219,11 @@ async def scan(
     except asyncio.CancelledError:
         await queue.join()
     queue.shutdown(immediate=True)
-    return results
+    # Jobs interrupted by the timeout may still extend results, so return a copy
+    paths = results[:max_results]
+    if sort:
+        paths.sort()
+    return paths
 
 
 class Scan:
@@ -7,117 +7,4134 @@ This class is used by the [command palette](/guide/command_palette) to match sea
 
 from __future__ import annotations
 
+from array import array
+from bisect import bisect_right
+from collections import Counter
+from concurrent.futures import ThreadPoolExecutor
+from dataclasses import dataclass, replace
+from enum import IntEnum
 from functools import lru_cache
+from heapq import nlargest
+from itertools import accumulate, count, groupby, islice, product
+import logging
+from math import log, sqrt
+import mmap
 from operator import itemgetter
+import os
+from os import PathLike
+from queue import Queue
+import re
 from re import finditer
-from typing import Iterable, Sequence
+from threading import Lock, RLock, local
+from time import perf_counter_ns
+from unicodedata import category, combining, normalize
+from typing import Callable, Iterable, Iterator, Literal, Mapping, Sequence, Self
 
 
 from textual.cache import LRUCache
 
+logger = logging.getLogger("fuzzy")
+
+type FirstLetterCurve = Literal["linear", "sqrt", "log"]
+"""How the number of first letter matches contributes to the score."""
+
+_LIMIT_FIELDS = frozenset(
+    {
+        "cache_size",
+        "max_queries",
+        "max_positions",
+        "max_candidates",
+    }
+)
+"""Config fields which must not be negative."""
+
+_POSITIVE_FIELDS = frozenset({"max_combinations", "parallel_chunk_size"})
+"""Config fields which must be at least 1."""
+
+_config_generations = count(1)
+"""Source of unique config generations."""
+
+
+@dataclass
+class FuzzyConfig:
+    """Configuration for a [FuzzySearch][toad.fuzzy.FuzzySearch].
+
+    Setters return the config, so they may be chained:
+
+    ```python
+    config = FuzzyConfig().set_path_mode().set_depth_penalty(0.5)
+    fuzzy_search = FuzzySearch.from_config(config)
+    ```
+    """
+
+    case_sensitive: bool = False
+    """Is the match case sensitive?"""
+    smart_case: bool = False
+    """Make the match case sensitive only if the query contains upper case letters?"""
+    path_mode: bool = False
+    """Score candidates as paths?"""
+    cache_size: int = 1024 * 4
+    """Number of queries to cache."""
+    cache_nonmatches: bool = False
+    """Cache results which don't match? Non-matches are typically cheap to recompute."""
+    max_queries: int | None = None
+    """Maximum number of distinct queries to retain in the cache, or `None` for no limit.
+    Results for the least recently used query are evicted together."""
+    depth_penalty: float = 0.0
+    """Path mode only. Reduce the score by this factor for each `/` preceding the first match."""
+    camel_case_boundaries: bool = False
+    """Treat a lower case letter followed by an upper case letter as the start of a word?"""
+    unicode_word_boundaries: bool = False
+    """Find the start of words with Unicode word boundary rules, rather than runs of
+    alphanumeric characters? Handles combining marks, and scripts without spaces."""
+    unicode_normalize: bool = False
+    """Normalize the query and candidate (to NFC), so composed and decomposed characters match?"""
+    contiguous_digits: bool = False
+    """Require runs of digits in the query to match contiguous characters in the candidate?"""
+    ignore_chars: str | None = None
+    """Characters to skip over in the candidate (and query), or `None` to match all characters."""
+    locale: str | None = None
+    """Locale for case insensitive matching (e.g. `"tr"` for Turkish dotted and dotless I),
+    or `None` for the default."""
+    equivalences: tuple[tuple[str, str], ...] | None = None
+    """Pairs of characters which match each other (e.g. `(("l", "1"), ("o", "0"))`), or `None`."""
+    normalize_path_separators: bool = False
+    """Path mode only. Treat `\\` as a path separator, in addition to `/`."""
+    collapse_separators: bool = False
+    """Path mode only. Ignore empty and `.` path components when finding the start of components?"""
+    skip_leading_separator: bool = False
+    """Path mode only. Treat the first character after the leading separator(s) of an
+    absolute path as the start of the first component, rather than the separator itself?"""
+    strip_prefix: str | None = None
+    """A prefix to remove from candidates before matching (e.g. a common root directory).
+    Offsets still refer to the original candidate."""
+    match_basename_only: bool = False
+    """Path mode only. Match against the last path component only?"""
+    max_positions: int | None = None
+    """Maximum number of offsets to return, or `None` for no limit. The score still reflects the full match."""
+    profiling: bool = False
+    """Record time spent matching?"""
+    slow_match_callback: Callable[[str, str], object] | None = None
+    """Callback invoked with the query and candidate when a match is slow."""
+    slow_match_threshold_ms: float = 100.0
+    """Time in milliseconds after which a match is considered slow."""
+    substring_only: bool = False
+    """Require the query to match contiguous characters in the candidate?"""
+    max_combinations: int | None = None
+    """Maximum number of alignments of the query to score for each candidate, or `None`
+    for no limit. Must be at least 1. Limiting alignments bounds the time to match
+    pathological candidates, at the cost of an approximate score."""
+    bag_match: bool = False
+    """Match the characters of the query in any order, scoring by how closely they are clustered?"""
+    prefix_fast_path: bool = False
+    """If the query is a prefix of the candidate, take the contiguous match at the start
+    without scoring other alignments? This is almost always the best alignment, but an
+    alignment with more matches at the start of words may score higher."""
+    greedy: bool = False
+    """Score only the first alignment of the query from left to right, rather than the best.
+    Much faster for long candidates, but scores may be lower than the best alignment.
+    `contiguous_digits` and `anchor_first_to_boundary` are ignored."""
+    line_aware: bool = False
+    """Prevent matches from spanning multiple lines?"""
+    parallel_chunk_size: int | None = None
+    """Match candidates longer than twice this size in chunks (in parallel if
+    `thread_count` is set), or `None` to always match the whole candidate. Each chunk
+    scores the alignments which start within it, so results are the same as matching
+    the whole candidate. Ignored (the candidate is matched whole) with `substring_only`,
+    `bag_match`, `prefix_fast_path`, `greedy`, `unicode_normalize`, or
+    `max_combinations`."""
+    thread_count: int | None = None
+    """Number of threads used to match chunks in parallel (see `parallel_chunk_size`),
+    or `None` to match chunks serially. Threads add overhead unless matching can run
+    without the GIL (e.g. on a free-threaded build). Call `FuzzySearch.close` to shut
+    down the threads."""
+    dedup_candidates: bool = False
+    """Match only the first occurrence of identical candidates in batch methods?"""
+    max_candidates: int | None = None
+    """Maximum number of candidates to process in batch methods, or `None` for no limit."""
+    first_letter_boost: float = 1.0
+    """Score added for each matched character at the start of a word (or path component)."""
+    first_letter_curve: FirstLetterCurve = "linear"
+    """Apply diminishing returns to the number of first letter matches: `"linear"` for none,
+    `"sqrt"` for the square root, or `"log"` for one plus the natural logarithm."""
+    interior_penalty: float = 0.0
+    """Score subtracted for each matched character which isn't at the start of a word
+    (the score of a match won't fall below `FuzzySearch.MIN_SCORE`)."""
+    min_run_length: int = 1
+    """Minimum number of consecutive matched characters required to contribute to the boost for fewer groups."""
+    min_match_score: float = 0.0
+    """Minimum score of any match, so penalized matches remain distinguishable from
+    non-matches. Thresholds (such as in `count_above`) below this value will accept
+    every match."""
+    length_penalty: float = 0.0
+    """Score subtracted for each character in the candidate outside of the span of the match
+    (the score of a match won't fall below `FuzzySearch.MIN_SCORE`)."""
+    token_coverage_boost: float = 0.0
+    """Multiply the score by one plus this factor times the fraction of words (or path
+    components) which contain a matched character."""
+    prefix_boost: float = 1.0
+    """Multiply the score by this factor if the match starts at the beginning of the candidate."""
+    extension_boost: float = 1.0
+    """Multiply the score by this factor if the query ends with the candidate's file extension (e.g. `.py`)."""
+    prefer_shorter: bool = False
+    """When sorting results, rank shorter candidates first if scores are equal?"""
+    prefer_early_match: bool = False
+    """When sorting results, rank candidates whose match starts earliest first if scores are equal?
+    Applied before `prefer_shorter`."""
+    relative_normalize: bool = False
+    """`match_batch` only. Divide scores by the best score in the batch, so the best match scores 1.0?"""
+    positional_bias: float = 0.0
+    """Batch matching only. Subtract this amount, multiplied by the candidate's index, from
+    each score, so earlier candidates win ties. Should be small enough that the total bias
+    is less than the smallest meaningful difference in scores (e.g. `1e-9`)."""
+    acronym_boost: float = 0.0
+    """Score added for each pair of consecutive matched characters at the start of consecutive words."""
+    acronym_fallback: float | None = None
+    """If a query doesn't match, match it against the first letters of words in the candidate,
+    and multiply the score by this factor. `None` to disable the fallback."""
+    anchor_first_to_boundary: bool = False
+    """Require the first matched character to be the first letter of a word (or path component)?"""
+    trailing_space_anchor: bool = False
+    """If the query ends with a space, require the last matched character to be at the end of a word?"""
+
+    def __setattr__(self, name: str, value: object) -> None:
+        if name in _LIMIT_FIELDS and isinstance(value, int):
+            _check_limit(name, value)
+        elif name in _POSITIVE_FIELDS and isinstance(value, int) and value < 1:
+            raise ValueError(f"{name} must be positive; got {value!r}")
+        super().__setattr__(name, value)
+        # Any change invalidates results computed with the previous config
+        super().__setattr__("_generation", next(_config_generations))
+
+    @property
+    def generation(self) -> int:
+        """A number which changes whenever the config is modified.
+
+        Generations are unique across all configs, so a generation identifies both
+        the config and its state.
+        """
+        return getattr(self, "_generation", 0)
+
+    @classmethod
+    def command_palette(cls) -> Self:
+        """A preset for matching short labels, such as commands in a command palette.
+
+        Enables camel case boundaries, smart case, a strong first letter and
+        acronym boost, and a boost for matches at the start of the label.
+
+        Returns:
+            A new config.
+        """
+        return cls(
+            smart_case=True,
+            camel_case_boundaries=True,
+            first_letter_boost=2.0,
+            acronym_boost=1.0,
+            prefix_boost=1.5,
+        )
+
+    @classmethod
+    def file_paths(cls) -> Self:
+        """A preset for matching file paths, such as in a file picker.
+
+        Enables path mode (which boosts matches in the last component), a depth
+        penalty of 0.1, `\\` as a separator, collapsing of repeated separators,
+        and ranking shorter paths first when scores are equal.
+
+        Returns:
+            A new config.
+        """
+        return cls(
+            path_mode=True,
+            depth_penalty=0.1,
+            normalize_path_separators=True,
+            collapse_separators=True,
+            prefer_shorter=True,
+        )
+
+    def set_case_sensitive(self, case_sensitive: bool = True) -> Self:
+        """Make the match case sensitive.
+
+        Args:
+            case_sensitive: Is the match case sensitive?
+
+        Returns:
+            The config.
+        """
+        self.case_sensitive = case_sensitive
+        return self
+
+    def set_smart_case(self, smart_case: bool = True) -> Self:
+        """Make the match case sensitive only if the query contains upper case letters.
+
+        Args:
+            smart_case: Enable smart case?
+
+        Returns:
+            The config.
+        """
+        self.smart_case = smart_case
+        return self
+
+    def set_path_mode(self, path_mode: bool = True) -> Self:
+        """Score candidates as paths.
+
+        Args:
+            path_mode: Score candidates as paths?
+
+        Returns:
+            The config.
+        """
+        self.path_mode = path_mode
+        return self
+
+    def set_cache_size(self, cache_size: int) -> Self:
+        """Set the number of queries to cache.
+
+        Args:
+            cache_size: Number of queries to cache.
+
+        Returns:
+            The config.
+        """
+        self.cache_size = cache_size
+        return self
+
+    def set_cache_nonmatches(self, cache_nonmatches: bool = True) -> Self:
+        """Cache results which don't match.
+
+        Args:
+            cache_nonmatches: Cache results which don't match?
+
+        Returns:
+            The config.
+        """
+        self.cache_nonmatches = cache_nonmatches
+        return self
+
+    def set_max_queries(self, max_queries: int | None) -> Self:
+        """Limit the number of distinct queries retained in the cache.
+
+        Args:
+            max_queries: Maximum number of queries, or `None` for no limit.
+
+        Returns:
+            The config.
+        """
+        self.max_queries = max_queries
+        return self
+
+    def set_depth_penalty(self, depth_penalty: float) -> Self:
+        """Set the penalty for each `/` preceding the first match (path mode only).
+
+        Args:
+            depth_penalty: Factor to reduce the score by, for each `/`.
+
+        Returns:
+            The config.
+        """
+        self.depth_penalty = depth_penalty
+        return self
+
+    def set_camel_case_boundaries(self, camel_case_boundaries: bool = True) -> Self:
+        """Treat lower to upper case transitions as the start of a word.
+
+        Args:
+            camel_case_boundaries: Enable camel case boundaries?
+
+        Returns:
+            The config.
+        """
+        self.camel_case_boundaries = camel_case_boundaries
+        return self
+
+    def set_unicode_word_boundaries(
+        self, unicode_word_boundaries: bool = True
+    ) -> Self:
+        """Find the start of words with Unicode word boundary rules.
+
+        Args:
+            unicode_word_boundaries: Enable Unicode word boundaries?
+
+        Returns:
+            The config.
+        """
+        self.unicode_word_boundaries = unicode_word_boundaries
+        return self
+
+    def set_unicode_normalize(self, unicode_normalize: bool = True) -> Self:
+        """Normalize the query and candidate to NFC.
+
+        Args:
+            unicode_normalize: Enable Unicode normalization?
+
+        Returns:
+            The config.
+        """
+        self.unicode_normalize = unicode_normalize
+        return self
+
+    def set_contiguous_digits(self, contiguous_digits: bool = True) -> Self:
+        """Require runs of digits to match contiguous characters.
+
+        Args:
+            contiguous_digits: Require contiguous digits?
+
+        Returns:
+            The config.
+        """
+        self.contiguous_digits = contiguous_digits
+        return self
+
+    def set_ignore_chars(self, ignore_chars: str | None) -> Self:
+        """Set characters to skip over when matching.
+
+        Args:
+            ignore_chars: Characters to skip, or `None` to match all characters.
+
+        Returns:
+            The config.
+        """
+        self.ignore_chars = ignore_chars
+        return self
+
+    def set_locale(self, locale: str | None) -> Self:
+        """Set the locale for case insensitive matching.
+
+        Args:
+            locale: Locale (e.g. `"tr"`), or `None` for the default.
+
+        Returns:
+            The config.
+        """
+        self.locale = locale
+        return self
+
+    def set_equivalences(self, equivalences: Iterable[tuple[str, str]] | None) -> Self:
+        """Set pairs of characters which match each other.
+
+        Args:
+            equivalences: Pairs of equivalent characters, or `None`.
+
+        Returns:
+            The config.
+        """
+        self.equivalences = (
+            None if equivalences is None else tuple(map(tuple, equivalences))
+        )
+        return self
+
+    def set_collapse_separators(self, collapse_separators: bool = True) -> Self:
+        """Ignore empty and `.` path components (path mode only).
+
+        Args:
+            collapse_separators: Collapse separators?
+
+        Returns:
+            The config.
+        """
+        self.collapse_separators = collapse_separators
+        return self
+
+    def set_skip_leading_separator(self, skip_leading_separator: bool = True) -> Self:
+        """Skip the leading separator(s) of absolute paths (path mode only).
+
+        Args:
+            skip_leading_separator: Skip leading separators?
+
+        Returns:
+            The config.
+        """
+        self.skip_leading_separator = skip_leading_separator
+        return self
+
+    def set_strip_prefix(self, strip_prefix: str | None) -> Self:
+        """Set a prefix to remove from candidates before matching.
+
+        Args:
+            strip_prefix: Prefix to remove, or `None`.
+
+        Returns:
+            The config.
+        """
+        self.strip_prefix = strip_prefix
+        return self
+
+    def set_match_basename_only(self, match_basename_only: bool = True) -> Self:
+        """Match against the last path component only (path mode only).
+
+        Args:
+            match_basename_only: Match the basename only?
+
+        Returns:
+            The config.
+        """
+        self.match_basename_only = match_basename_only
+        return self
+
+    def set_max_positions(self, max_positions: int | None) -> Self:
+        """Limit the number of offsets returned for a match.
+
+        Args:
+            max_positions: Maximum number of offsets, or `None` for no limit.
+
+        Returns:
+            The config.
+        """
+        self.max_positions = max_positions
+        return self
+
+    def set_substring_only(self, substring_only: bool = True) -> Self:
+        """Require the query to match contiguous characters.
+
+        Args:
+            substring_only: Match substrings only?
+
+        Returns:
+            The config.
+        """
+        self.substring_only = substring_only
+        return self
+
+    def set_max_combinations(self, max_combinations: int | None) -> Self:
+        """Limit the number of alignments scored for each candidate.
+
+        Args:
+            max_combinations: Maximum number of alignments (at least 1), or `None`
+                for no limit.
+
+        Returns:
+            The config.
+        """
+        self.max_combinations = max_combinations
+        return self
+
+    def set_bag_match(self, bag_match: bool = True) -> Self:
+        """Match the characters of the query in any order.
+
+        Args:
+            bag_match: Enable bag matching?
+
+        Returns:
+            The config.
+        """
+        self.bag_match = bag_match
+        return self
+
+    def set_prefix_fast_path(self, prefix_fast_path: bool = True) -> Self:
+        """Take the contiguous match at the start if the query is a prefix.
+
+        Args:
+            prefix_fast_path: Enable the prefix fast path?
+
+        Returns:
+            The config.
+        """
+        self.prefix_fast_path = prefix_fast_path
+        return self
+
+    def set_greedy(self, greedy: bool = True) -> Self:
+        """Score only the first alignment of the query.
+
+        Args:
+            greedy: Enable greedy matching?
+
+        Returns:
+            The config.
+        """
+        self.greedy = greedy
+        return self
+
+    def set_line_aware(self, line_aware: bool = True) -> Self:
+        """Prevent matches from spanning multiple lines.
+
+        Args:
+            line_aware: Enable line aware matching?
+
+        Returns:
+            The config.
+        """
+        self.line_aware = line_aware
+        return self
+
+    def set_parallel_chunks(self, chunk_size: int | None) -> Self:
+        """Match long candidates in chunks (in parallel if a thread count is set).
+
+        Args:
+            chunk_size: Size of each chunk, or `None` to match the whole candidate.
+
+        Returns:
+            The config.
+        """
+        self.parallel_chunk_size = chunk_size
+        return self
+
+    def set_thread_count(self, thread_count: int | None) -> Self:
+        """Set the number of threads used for parallel matching.
+
+        Args:
+            thread_count: Number of threads, or `None` to match serially.
+
+        Returns:
+            The config.
+        """
+        self.thread_count = thread_count
+        return self
+
+    def set_dedup_candidates(self, dedup_candidates: bool = True) -> Self:
+        """Match only the first occurrence of identical candidates in batch methods.
+
+        Args:
+            dedup_candidates: Deduplicate candidates?
+
+        Returns:
+            The config.
+        """
+        self.dedup_candidates = dedup_candidates
+        return self
+
+    def set_max_candidates(self, max_candidates: int | None) -> Self:
+        """Limit the number of candidates processed in batch methods.
+
+        Args:
+            max_candidates: Maximum number of candidates, or `None` for no limit.
+
+        Returns:
+            The config.
+        """
+        self.max_candidates = max_candidates
+        return self
+
+    def set_first_letter_boost(self, first_letter_boost: float) -> Self:
+        """Set the score added for each match at the start of a word.
+
+        Args:
+            first_letter_boost: Score for each first letter match.
+
+        Returns:
+            The config.
+        """
+        self.first_letter_boost = first_letter_boost
+        return self
+
+    def set_first_letter_curve(self, first_letter_curve: FirstLetterCurve) -> Self:
+        """Set how the number of first letter matches contributes to the score.
+
+        Args:
+            first_letter_curve: `"linear"`, `"sqrt"`, or `"log"`.
+
+        Returns:
+            The config.
+        """
+        self.first_letter_curve = first_letter_curve
+        return self
+
+    def set_interior_penalty(self, interior_penalty: float) -> Self:
+        """Set the score subtracted for each match which isn't at the start of a word.
+
+        Args:
+            interior_penalty: Penalty for each interior match.
+
+        Returns:
+            The config.
+        """
+        self.interior_penalty = interior_penalty
+        return self
+
+    def set_min_run_length(self, min_run_length: int) -> Self:
+        """Set the minimum run of consecutive matches which contributes to the boost.
+
+        Args:
+            min_run_length: Minimum number of consecutive matched characters.
+
+        Returns:
+            The config.
+        """
+        self.min_run_length = min_run_length
+        return self
+
+    def set_min_match_score(self, min_match_score: float) -> Self:
+        """Set the minimum score of any match.
+
+        Args:
+            min_match_score: Minimum score.
+
+        Returns:
+            The config.
+        """
+        self.min_match_score = min_match_score
+        return self
+
+    def set_length_penalty(self, length_penalty: float) -> Self:
+        """Set the score subtracted for each character outside of the match.
+
+        Args:
+            length_penalty: Penalty for each character outside of the span of the match.
+
+        Returns:
+            The config.
+        """
+        self.length_penalty = length_penalty
+        return self
+
+    def set_token_coverage_boost(self, token_coverage_boost: float) -> Self:
+        """Set the boost for matching characters in many words.
+
+        Args:
+            token_coverage_boost: Factor applied to the fraction of words matched.
+
+        Returns:
+            The config.
+        """
+        self.token_coverage_boost = token_coverage_boost
+        return self
+
+    def set_prefix_boost(self, prefix_boost: float) -> Self:
+        """Set the boost for matches at the beginning of the candidate.
+
+        Args:
+            prefix_boost: Factor to multiply the score by.
+
+        Returns:
+            The config.
+        """
+        self.prefix_boost = prefix_boost
+        return self
+
+    def set_extension_boost(self, extension_boost: float) -> Self:
+        """Set the boost for queries ending with the candidate's file extension.
+
+        Args:
+            extension_boost: Factor to multiply the score by.
+
+        Returns:
+            The config.
+        """
+        self.extension_boost = extension_boost
+        return self
+
+    def set_prefer_shorter(self, prefer_shorter: bool = True) -> Self:
+        """Rank shorter candidates first if scores are equal.
+
+        Args:
+            prefer_shorter: Prefer shorter candidates?
+
+        Returns:
+            The config.
+        """
+        self.prefer_shorter = prefer_shorter
+        return self
+
+    def set_prefer_early_match(self, prefer_early_match: bool = True) -> Self:
+        """Rank candidates whose match starts earliest first if scores are equal.
+
+        Args:
+            prefer_early_match: Prefer early matches?
+
+        Returns:
+            The config.
+        """
+        self.prefer_early_match = prefer_early_match
+        return self
+
+    def set_relative_normalize(self, relative_normalize: bool = True) -> Self:
+        """Divide scores in `match_batch` by the best score in the batch.
+
+        Args:
+            relative_normalize: Normalize scores?
+
+        Returns:
+            The config.
+        """
+        self.relative_normalize = relative_normalize
+        return self
+
+    def set_positional_bias(self, positional_bias: float) -> Self:
+        """Set the bias towards earlier candidates in batch matching.
+
+        Args:
+            positional_bias: Amount subtracted for each index.
+
+        Returns:
+            The config.
+        """
+        self.positional_bias = positional_bias
+        return self
+
+    def set_acronym_boost(self, acronym_boost: float) -> Self:
+        """Set the score added for matches at the start of consecutive words.
+
+        Args:
+            acronym_boost: Score for each acronym pair.
+
+        Returns:
+            The config.
+        """
+        self.acronym_boost = acronym_boost
+        return self
+
+    def set_acronym_fallback(self, acronym_fallback: float | None = 0.5) -> Self:
+        """Match against the first letters of words if the query doesn't match.
+
+        Args:
+            acronym_fallback: Factor to multiply the score by, or `None` to disable.
+
+        Returns:
+            The config.
+        """
+        self.acronym_fallback = acronym_fallback
+        return self
+
+    def set_anchor_first_to_boundary(
+        self, anchor_first_to_boundary: bool = True
+    ) -> Self:
+        """Require the first matched character to be the start of a word.
+
+        Args:
+            anchor_first_to_boundary: Anchor the first match?
+
+        Returns:
+            The config.
+        """
+        self.anchor_first_to_boundary = anchor_first_to_boundary
+        return self
+
+    def set_trailing_space_anchor(self, trailing_space_anchor: bool = True) -> Self:
+        """Anchor the last match to the end of a word if the query ends with a space.
+
+        Args:
+            trailing_space_anchor: Enable the trailing space anchor?
+
+        Returns:
+            The config.
+        """
+        self.trailing_space_anchor = trailing_space_anchor
+        return self
+
+    def set_profiling(self, profiling: bool = True) -> Self:
+        """Record time spent matching.
+
+        Args:
+            profiling: Enable profiling?
+
+        Returns:
+            The config.
+        """
+        self.profiling = profiling
+        return self
+
+    def set_slow_match_callback(
+        self,
+        slow_match_callback: Callable[[str, str], object] | None,
+        threshold_ms: float = 100.0,
+    ) -> Self:
+        """Set a callback invoked with the query and candidate when a match is slow.
+
+        Args:
+            slow_match_callback: Callback, or `None` to disable.
+            threshold_ms: Time in milliseconds after which a match is considered slow.
+
+        Returns:
+            The config.
+        """
+        self.slow_match_callback = slow_match_callback
+        self.slow_match_threshold_ms = threshold_ms
+        return self
+
+    def set_normalize_path_separators(
+        self, normalize_path_separators: bool = True
+    ) -> Self:
+        """Treat `\\` as a path separator (path mode only).
+
+        Args:
+            normalize_path_separators: Normalize path separators?
+
+        Returns:
+            The config.
+        """
+        self.normalize_path_separators = normalize_path_separators
+        return self
+
+
+_EXTENSION_RE = re.compile(r"(?<=[^./\\])\.\w+$")
+"""Matches a file extension at the end of a query."""
+
+
+_TURKIC_CASEFOLD_TABLE = str.maketrans({"I": "ı", "İ": "i"})
+"""Translation table for the dotted and dotless I in Turkish and Azerbaijani."""
+
+
+def _is_turkic_locale(locale: str) -> bool:
+    """Check if a locale uses the Turkish rules for the dotted and dotless I.
+
+    Args:
+        locale: A locale, such as "tr" or "tr_TR".
+
+    Returns:
+        `True` for Turkish or Azerbaijani.
+    """
+    return re.split(r"[-_.]", locale, maxsplit=1)[0].lower() in ("tr", "az")
+
+
+_WORD_MID_CHARACTERS = frozenset("'.:·’‘״")
+"""Characters which don't break a word between two letters (e.g. "can't")."""
+
+
+def _get_word_character_kind(character: str) -> str | None:
+    """Classify a character for Unicode word boundary detection.
+
+    This is a simplification of the rules in Unicode Standard Annex #29.
+
+    Args:
+        character: A single character.
+
+    Returns:
+        One of "letter", "katakana", "ideograph", "mark", "mid", or `None` for
+            characters which aren't part of a word.
+    """
+    code_point = ord(character)
+    if 0x30A0 <= code_point <= 0x30FF or 0x31F0 <= code_point <= 0x31FF:
+        return "katakana"
+    if (
+        0x3040 <= code_point <= 0x309F
+        or 0x3400 <= code_point <= 0x9FFF
+        or 0xF900 <= code_point <= 0xFAFF
+        or 0x20000 <= code_point <= 0x3FFFF
+    ):
+        return "ideograph"
+    character_category = category(character)
+    if character_category[0] in "LN" or character_category == "Pc":
+        return "letter"
+    if character_category[0] == "M":
+        return "mark"
+    if character in _WORD_MID_CHARACTERS:
+        return "mid"
+    return None
+
+
+def _map_offsets(index_map: list[int] | None, offsets: Iterable[int]) -> list[int]:
+    """Map offsets in normalized text on to offsets in the original text.
+
+    Normalization may expand a character (e.g. casefolding "ß" to "ss"), so several
+    offsets can map on to the same original offset. Repeated offsets are removed.
+
+    Args:
+        index_map: Offset in the original text for each normalized character, or
+            `None` if the offsets are unchanged.
+        offsets: Offsets in the normalized text, in ascending order.
+
+    Returns:
+        Unique offsets in the original text.
+    """
+    if index_map is None:
+        return list(offsets)
+    return list(dict.fromkeys(index_map[offset] for offset in offsets))
+
+
+def _check_limit(name: str, limit: int | None) -> None:
+    """Check a limit on the number of results is valid.
+
+    Args:
+        name: Name of the parameter, for the error message.
+        limit: Maximum number of results, or `None` for no limit.
+
+    Raises:
+        ValueError: If the limit is negative.
+    """
+    if limit is not None and limit < 0:
+        raise ValueError(f"{name} must not be negative; got {limit!r}")
+
+
+@lru_cache(maxsize=64)
+def _compile_glob(pattern: str) -> re.Pattern[str]:
+    """Compile a glob pattern to a regular expression, with a group for each literal
+    character.
+
+    Args:
+        pattern: A pattern where `*` matches any run of characters, and `?` matches
+            any single character.
+
+    Returns:
+        A compiled regular expression.
+    """
+    regex: list[str] = []
+    for character in pattern:
+        if character == "*":
+            # Non-greedy, so literal characters match as early as possible
+            regex.append(".*?")
+        elif character == "?":
+            regex.append(".")
+        else:
+            regex.append(f"({re.escape(character)})")
+    return re.compile("".join(regex), re.DOTALL)
+
+
+@lru_cache(maxsize=64)
+def _build_equivalence_table(
+    equivalences: tuple[tuple[str, str], ...], casefold: bool
+) -> dict[int, str]:
+    """Build a translation table which maps equivalent characters on to a single character.
+
+    Args:
+        equivalences: Pairs of characters which should be considered the same.
+        casefold: Casefold the characters first (for case insensitive matching)?
+
+    Raises:
+        ValueError: If an equivalence isn't a pair of single characters.
+
+    Returns:
+        A table for `str.translate`.
+    """
+    groups: dict[str, frozenset[str]] = {}
+    for pair in equivalences:
+        if len(pair) != 2 or not all(len(character) == 1 for character in pair):
+            raise ValueError(
+                f"equivalences must be pairs of single characters; got {pair!r}"
+            )
+        if casefold:
+            pair = tuple(
+                folded if len(folded := character.casefold()) == 1 else character
+                for character in pair
+            )
+        first, second = pair
+        group = groups.get(first, frozenset(first)) | groups.get(
+            second, frozenset(second)
+        )
+        for character in group:
+            groups[character] = group
+    return {ord(character): min(group) for character, group in groups.items()}
+
+
+def diff_candidates(
+    old: Sequence[str], new: Sequence[str]
+) -> tuple[list[str], list[str]]:
+    """Compare two lists of candidates.
+
+    Args:
+        old: Previous candidates.
+        new: New candidates.
+
+    Returns:
+        A tuple of (added, removed) candidates, in the order they appear in `new` and `old`.
+    """
+    old_set = set(old)
+    new_set = set(new)
+    added = [candidate for candidate in new if candidate not in old_set]
+    removed = [candidate for candidate in old if candidate not in new_set]
+    return added, removed
+
+
+class MatchTier(IntEnum):
+    """The quality of a match, from best to worst."""
+
+    EXACT = 4
+    """The query matches the entire candidate."""
+    PREFIX = 3
+    """The query matches the start of the candidate."""
+    SUBSTRING = 2
+    """The query matches contiguous characters in the candidate."""
+    FUZZY = 1
+    """The query matches scattered characters in the candidate."""
+    NONE = 0
+    """No match."""
+
+
+@dataclass(frozen=True)
+class MatchResult:
+    """The result of a fuzzy match."""
+
+    score: float
+    """The match score, or `0` for no match."""
+    positions: Sequence[int]
+    """Offsets of matched characters in the candidate."""
+
+    def __bool__(self) -> bool:
+        return bool(self.score)
+
+
+class NormalizationMap:
+    """Text which is normalized in stages, and a map of each character on to its offset
+    in the original text.
+
+    Stages may change the length of the text, and the map is composed through each
+    stage, so offsets in the normalized text can always be mapped back to the original.
+    """
+
+    def __init__(self, text: str) -> None:
+        """
+        Args:
+            text: The original text.
+        """
+        self.text = text
+        """The normalized text."""
+        self.index_map: list[int] | None = None
+        """Offset in the original text for each character, or `None` if unchanged."""
+
+    def _apply(self, text: str, index_map: list[int]) -> None:
+        """Replace the text with the result of a stage.
+
+        Args:
+            text: The new text.
+            index_map: Offset in the current text for each character in the new text.
+        """
+        if (current_map := self.index_map) is not None:
+            index_map = [current_map[index] for index in index_map]
+        self.text = text
+        self.index_map = index_map
+
+    def original_offsets(self, offsets: Iterable[int]) -> list[int]:
+        """Map offsets in the normalized text on to offsets in the original text.
+
+        Args:
+            offsets: Offsets in the normalized text, in ascending order.
+
+        Returns:
+            Unique offsets in the original text.
+        """
+        return _map_offsets(self.index_map, offsets)
+
+    def map_characters(self, function: Callable[[str], str]) -> None:
+        """Apply a function to the text, character by character.
+
+        Args:
+            function: A function which maps a character on to one or more characters.
+        """
+        text = self.text
+        mapped_text = function(text)
+        if len(mapped_text) == len(text):
+            # One character for one character, so offsets are unchanged
+            self.text = mapped_text
+            return
+        characters: list[str] = []
+        index_map: list[int] = []
+        for index, character in enumerate(text):
+            mapped = function(character)
+            characters.append(mapped)
+            index_map.extend([index] * len(mapped))
+        self._apply("".join(characters), index_map)
+
+    def slice(self, start: int) -> None:
+        """Remove the start of the text.
+
+        Args:
+            start: Offset of the first character to keep.
+        """
+        self._apply(self.text[start:], list(range(start, len(self.text))))
+
+    def normalize_unicode(self) -> None:
+        """Normalize the text to NFC."""
+        text = self.text
+        normalized: list[str] = []
+        index_map: list[int] = []
+        segment_start = 0
+        # Normalize each base character along with any following combining characters
+        for index in range(1, len(text) + 1):
+            if index == len(text) or not combining(text[index]):
+                segment = normalize("NFC", text[segment_start:index])
+                normalized.append(segment)
+                index_map.extend([segment_start] * len(segment))
+                segment_start = index
+        self._apply("".join(normalized), index_map)
+
+    def remove(self, characters: str) -> None:
+        """Remove characters from the text.
+
+        Args:
+            characters: Characters to remove.
+        """
+        text = self.text
+        kept_indices = [
+            index
+            for index, character in enumerate(text)
+            if character not in characters
+        ]
+        self._apply("".join(text[index] for index in kept_indices), kept_indices)
+
+
+@dataclass(frozen=True)
+class CompiledQuery:
+    """A query which has been validated and normalized once, for repeated matching.
+
+    Create with [compile_query][toad.fuzzy.FuzzySearch.compile_query].
+    """
+
+    query: str
+    """The original query."""
+    normalized_query: str
+    """The query after normalization."""
+    config_generation: int
+    """Generation of the config the query was normalized with."""
+
+
+@dataclass
+class ScoreBreakdown:
+    """The components of a fuzzy match score."""
+
+    offset_count: int
+    """Number of matched characters."""
+    first_letter_matches: int
+    """Number of matched characters at the start of a word (or path component)."""
+    weighted_first_letter_matches: float
+    """`first_letter_matches` with `first_letter_curve` applied."""
+    first_letter_boost: float
+    """Score added for each weighted first letter match."""
+    interior_matches: int
+    """Number of matched characters which aren't at the start of a word."""
+    interior_penalty: float
+    """Score subtracted for each interior match."""
+    acronym_pairs: int
+    """Number of consecutive matched characters at the start of consecutive words."""
+    acronym_boost: float
+    """Score added for each acronym pair."""
+    group_count: int
+    """Number of runs of consecutive matched characters."""
+    consecutive_boost: float
+    """Multiplier to favor fewer groups."""
+    prefix_boost: float
+    """Multiplier for matches at the start of the candidate."""
+    token_coverage: float
+    """Fraction of words containing a matched character (`0` unless `token_coverage_boost` is set)."""
+    token_coverage_boost: float
+    """Factor applied to `token_coverage`."""
+    path_boost: float
+    """Multiplier from path mode scoring (depth penalty and last component boost)."""
+    excess_length: int
+    """Number of characters in the candidate outside of the span of the match."""
+    length_penalty: float
+    """Score subtracted for each character of `excess_length`."""
+    acronym_multiplier: float
+    """Multiplier for a match on the first letters of words, when the query didn't
+    otherwise match (see `acronym_fallback`)."""
+    extension_boost: float
+    """Multiplier for queries which end with the candidate's file extension."""
+    min_score: float
+    """Lowest score after penalties (see `FuzzySearch.MIN_SCORE`)."""
+    min_match_score: float
+    """Minimum score of any match."""
+    final_score: float
+    """The resulting score."""
+
 
 class FuzzySearch:
     """Performs a fuzzy search.
 
-    Unlike a regex solution, this will finds all possible matches.
-    """
+    Unlike a regex solution, this will finds all possible matches.
+    """
+
+    MAX_RECENT_QUERIES = 256
+    """Maximum number of queries to remember for `recent_queries`."""
+    MAX_CAPPED_MATCHES = 256
+    """Maximum number of matches to remember for `capped_matches`."""
+    MIN_SCORE = 0.01
+    """Lowest score of a match, after penalties."""
+
+    def __init__(
+        self,
+        case_sensitive: bool = False,
+        *,
+        path_mode: bool = False,
+        cache_size: int = 1024 * 4,
+        config: FuzzyConfig | None = None,
+    ) -> None:
+        """Initialize fuzzy search.
+
+        Args:
+            case_sensitive: Is the match case sensitive?
+            path_mode: Score candidates as paths?
+            cache_size: Number of queries to cache.
+            config: Full configuration, which replaces the other arguments if given.
+        """
+        if config is None:
+            config = FuzzyConfig(
+                case_sensitive=case_sensitive,
+                path_mode=path_mode,
+                cache_size=cache_size,
+            )
+        self.config = replace(config)
+        self.cache: LRUCache[tuple[str, str], tuple[float, Sequence[int]]] = LRUCache(
+            self.config.cache_size
+        )
+        self._cache_size = self.config.cache_size
+        self._cache_lock = Lock()
+        # Guards the config sync, and precomputed candidate data
+        self._config_lock = RLock()
+        # Guards recent queries, capped matches, and profiling counters
+        self._state_lock = Lock()
+        self._executor_lock = Lock()
+        self._match_time_ns = 0
+        self._candidates_processed = 0
+        self._skipped_candidates = 0
+        self._recent_queries: dict[str, None] = {}
+        self._capped_matches: dict[tuple[str, str], None] = {}
+        self._cached_queries: dict[str, None] = {}
+        self._candidates: list[str] = []
+        self._candidate_first_letters: dict[str, frozenset[int]] = {}
+        self._synced_config = self.config
+        self._config_generation = self.config.generation
+        self._first_letters_functions = self._resolve_first_letters_functions()
+        self._mode_override = local()
+        self._executor: ThreadPoolExecutor | None = None
+        self._executor_thread_count: int | None = None
+
+    @classmethod
+    def from_config(cls, config: FuzzyConfig) -> Self:
+        """Create a fuzzy search from a config.
+
+        Args:
+            config: Fuzzy search configuration.
+
+        Returns:
+            A new fuzzy search instance.
+        """
+        return cls(config=config)
+
+    @classmethod
+    def for_command_palette(cls) -> Self:
+        """Create a fuzzy search suitable for a command palette.
+
+        See [FuzzyConfig.command_palette][toad.fuzzy.FuzzyConfig.command_palette].
+
+        Returns:
+            A new fuzzy search instance.
+        """
+        return cls.from_config(FuzzyConfig.command_palette())
+
+    @classmethod
+    def for_file_paths(cls) -> Self:
+        """Create a fuzzy search suitable for a file picker.
+
+        See [FuzzyConfig.file_paths][toad.fuzzy.FuzzyConfig.file_paths].
+
+        Returns:
+            A new fuzzy search instance.
+        """
+        return cls.from_config(FuzzyConfig.file_paths())
+
+    @property
+    def case_sensitive(self) -> bool:
+        """Is the match case sensitive?"""
+        return self.config.case_sensitive
+
+    def is_case_sensitive(self, query: str) -> bool:
+        """Is the match case sensitive for the given query?
+
+        Args:
+            query: The fuzzy query.
+
+        Returns:
+            `True` if the match should be case sensitive.
+        """
+        if self.config.case_sensitive:
+            return True
+        return self.config.smart_case and any(
+            character.isupper() for character in query
+        )
+
+    @property
+    def thread_count(self) -> int:
+        """Number of threads used for parallel matching (`1` if matching serially)."""
+        if (thread_count := self.config.thread_count) is not None:
+            return thread_count
+        return 1
+
+    def _get_executor(self) -> ThreadPoolExecutor | None:
+        """Get the thread pool used for parallel matching.
+
+        The pool is created on first use, and reused until the thread count changes.
+        Parallel matching is opt-in, since threads add overhead unless matching can
+        run without the GIL.
+
+        Returns:
+            A thread pool executor, or `None` if `thread_count` isn't set in the config,
+                or a pool couldn't be created (matching should be done serially).
+        """
+        if (thread_count := self.config.thread_count) is None:
+            self.close()
+            return None
+        with self._executor_lock:
+            if self._executor_thread_count != thread_count:
+                if self._executor is not None:
+                    self._executor.shutdown(wait=False)
+                    self._executor = None
+                self._executor_thread_count = thread_count
+                try:
+                    self._executor = ThreadPoolExecutor(
+                        thread_count, thread_name_prefix="fuzzy-search"
+                    )
+                except (ValueError, RuntimeError) as error:
+                    logger.warning(
+                        f"Unable to match in parallel, matching serially; {error}"
+                    )
+            return self._executor
+
+    def close(self) -> None:
+        """Shut down any thread pools used for parallel matching."""
+        with self._executor_lock:
+            executor = self._executor
+            self._executor = None
+            self._executor_thread_count = None
+        # Shut down outside of the lock, as pending work may need to get the executor
+        if executor is not None:
+            executor.shutdown()
+
+    def clear_cache(self) -> None:
+        """Clear cached results.
+
+        This is called automatically if the config is modified or replaced. The cache
+        is resized only if `cache_size` changed, so a cache which was grown keeps its
+        size.
+        """
+        with self._config_lock:
+            with self._cache_lock:
+                if self._cache_size != self.config.cache_size:
+                    self._cache_size = self.config.cache_size
+                    self.cache = LRUCache(self._cache_size)
+                else:
+                    self.cache.clear()
+                self._cached_queries.clear()
+            self._synced_config = config = self.config
+            self._config_generation = config.generation
+            self._first_letters_functions = self._resolve_first_letters_functions()
+            if self._candidates:
+                # Boundaries may depend on the config
+                self._candidate_first_letters = {}
+                get_first_letters = self.get_first_letters
+                self._candidate_first_letters = {
+                    candidate: get_first_letters(candidate)
+                    for candidate in self._candidates
+                }
+
+    def _is_config_synced(self) -> bool:
+        """Check if cached results were computed with the current config.
+
+        Returns:
+            `True` if the config is unchanged since the cache was last cleared.
+        """
+        config = self.config
+        return (
+            config is self._synced_config
+            and config.generation == self._config_generation
+        )
+
+    def _check_config(self) -> None:
+        """Clear the cache if the config has changed since results were cached."""
+        if self._is_config_synced():
+            return
+        with self._config_lock:
+            # Another thread may have cleared the cache while we waited for the lock
+            if not self._is_config_synced():
+                self.clear_cache()
+
+    @property
+    def candidates(self) -> list[str]:
+        """Candidates set with [set_candidates][toad.fuzzy.FuzzySearch.set_candidates]."""
+        return self._candidates
+
+    def set_candidates(self, candidates: Iterable[str]) -> None:
+        """Set the candidates which will be searched repeatedly.
+
+        This precomputes data used in scoring, which is retained until the candidates are replaced.
+
+        Args:
+            candidates: Candidate strings.
+        """
+        with self._config_lock:
+            self._candidates = list(candidates)
+            self._candidate_first_letters = {}
+            get_first_letters = self.get_first_letters
+            self._candidate_first_letters = {
+                candidate: get_first_letters(candidate)
+                for candidate in self._candidates
+            }
+
+    @property
+    def skipped_candidates(self) -> int:
+        """Number of candidates not processed by the most recent batch, due to `max_candidates`."""
+        return self._skipped_candidates
+
+    def total_match_time_ns(self) -> int:
+        """Total time spent matching, in nanoseconds (requires `profiling` in config)."""
+        return self._match_time_ns
+
+    def total_candidates_processed(self) -> int:
+        """Total number of candidates matched (requires `profiling` in config)."""
+        return self._candidates_processed
+
+    def reset_profile(self) -> None:
+        """Reset profiling counters."""
+        with self._state_lock:
+            self._match_time_ns = 0
+            self._candidates_processed = 0
+
+    def query_count(self) -> int:
+        """Number of distinct (normalized) queries with results in the cache."""
+        with self._cache_lock:
+            return len({query for query, _candidate in self.cache.keys()})
+
+    def _touch_cached_query(self, query: str) -> None:
+        """Mark a query as most recently used, and evict the oldest queries if there are
+        more than `max_queries`.
+
+        Should be called with the cache lock held.
+
+        Args:
+            query: A normalized query.
+        """
+        if (max_queries := self.config.max_queries) is None:
+            return
+        cached_queries = self._cached_queries
+        cached_queries.pop(query, None)
+        cached_queries[query] = None
+        if len(cached_queries) <= max_queries:
+            return
+        evicted: set[str] = set()
+        while len(cached_queries) > max_queries:
+            evicted_query = next(iter(cached_queries))
+            del cached_queries[evicted_query]
+            evicted.add(evicted_query)
+        cache = self.cache
+        for cache_key in [key for key in cache.keys() if key[0] in evicted]:
+            cache.discard(cache_key)
+
+    def match(
+        self, query: str | CompiledQuery, candidate: str
+    ) -> tuple[float, Sequence[int]]:
+        """Match against a query.
+
+        Args:
+            query: The fuzzy query, or a compiled query.
+            candidate: A candidate to check,.
+
+        Returns:
+            A pair of (score, tuple of offsets). `(0, ())` for no result.
+        """
+        config = self.config
+        normalized = False
+        if isinstance(query, CompiledQuery):
+            original_query = query.query
+            normalized = query.config_generation == config.generation
+            match_query = query.normalized_query if normalized else original_query
+        else:
+            original_query = match_query = query
+        self._add_recent_query(original_query)
+        if config.profiling or config.slow_match_callback is not None:
+            start_time = perf_counter_ns()
+            result = self._match_cached(match_query, candidate, normalized)
+            elapsed_ns = perf_counter_ns() - start_time
+            if config.profiling:
+                with self._state_lock:
+                    self._match_time_ns += elapsed_ns
+                    self._candidates_processed += 1
+            if (
+                config.slow_match_callback is not None
+                and elapsed_ns >= config.slow_match_threshold_ms * 1_000_000
+            ):
+                config.slow_match_callback(original_query, candidate)
+            return result
+        return self._match_cached(match_query, candidate, normalized)
+
+    def _match_cached(
+        self, query: str, candidate: str, normalized: bool = False
+    ) -> tuple[float, Sequence[int]]:
+        """Match against a query, using the cache.
+
+        Args:
+            query: The fuzzy query.
+            candidate: A candidate to check.
+            normalized: Has the query already been normalized?
+
+        Returns:
+            A pair of (score, tuple of offsets). `(0, ())` for no result.
+        """
+        self._check_config()
+        if not normalized:
+            query = self.normalize_query(query)
+        cache_key = (query, candidate)
+        # The cache may be shared by threads matching in parallel
+        with self._cache_lock:
+            if cache_key in self.cache:
+                self._touch_cached_query(query)
+                return self.cache[cache_key]
+        result = self._best_match(query, candidate, normalized=True)
+        if (max_positions := self.config.max_positions) is not None:
+            score, positions = result
+            result = (score, positions[:max_positions])
+        if result[0] or self.config.cache_nonmatches:
+            with self._cache_lock:
+                # Only queries with cached results count towards `max_queries`
+                self._touch_cached_query(query)
+                self.cache[cache_key] = result
+        return result
+
+    def casefold(self, text: str) -> str:
+        """Casefold text for case insensitive matching, according to the locale in the config.
+
+        Args:
+            text: Text to casefold.
+
+        Returns:
+            Casefolded text.
+        """
+        if (locale := self.config.locale) is not None and _is_turkic_locale(locale):
+            text = text.translate(_TURKIC_CASEFOLD_TABLE)
+        return text.casefold()
+
+    def compile_query(self, query: str) -> CompiledQuery:
+        """Validate and normalize a query once, for matching against many candidates.
+
+        The compiled query may be passed to [match][toad.fuzzy.FuzzySearch.match] and the
+        batch methods in place of a string. If the config is modified, the query will be
+        normalized again on each match.
+
+        Args:
+            query: The fuzzy query.
+
+        Raises:
+            ValueError: If the query has no characters to match.
+
+        Returns:
+            A compiled query.
+        """
+        normalized_query = self.normalize_query(query)
+        if not normalized_query.strip():
+            raise ValueError(f"query has no characters to match; got {query!r}")
+        return CompiledQuery(
+            query=query,
+            normalized_query=normalized_query,
+            config_generation=self.config.generation,
+        )
+
+    def _normalize_candidate(
+        self, candidate: str, case_sensitive: bool
+    ) -> NormalizationMap:
+        """Normalize the case and equivalent characters of a candidate, for comparison
+        with a normalized query.
+
+        Args:
+            candidate: The candidate string.
+            case_sensitive: Is the match case sensitive?
+
+        Returns:
+            Normalized candidate.
+        """
+        normalization = NormalizationMap(candidate)
+        if not case_sensitive:
+            normalization.map_characters(self.casefold)
+        if equivalences := self.config.equivalences:
+            table = _build_equivalence_table(equivalences, not case_sensitive)
+            normalization.map_characters(lambda text: text.translate(table))
+        return normalization
+
+    def normalize_query(self, query: str) -> str:
+        """Normalize a query according to the config.
+
+        Queries which normalize to the same string will produce the same results.
+
+        Args:
+            query: The fuzzy query.
+
+        Returns:
+            Normalized query.
+        """
+        case_sensitive = self.is_case_sensitive(query)
+        if not case_sensitive:
+            query = self.casefold(query)
+        if equivalences := self.config.equivalences:
+            query = query.translate(
+                _build_equivalence_table(equivalences, not case_sensitive)
+            )
+        if self.config.unicode_normalize:
+            query = normalize("NFC", query)
+        if ignore_chars := self.config.ignore_chars:
+            if not case_sensitive:
+                ignore_chars = ignore_chars.casefold()
+            query = "".join(
+                character for character in query if character not in ignore_chars
+            )
+        return query
+
+    def _best_match(
+        self,
+        query: str,
+        candidate: str,
+        breakdowns: list[ScoreBreakdown] | None = None,
+        normalized: bool = False,
+    ) -> tuple[float, Sequence[int]]:
+        """Get the best match (without caching).
+
+        Args:
+            query: The fuzzy query.
+            candidate: A candidate to check.
+            breakdowns: A list to receive the breakdown of the score, or `None` if
+                not required.
+            normalized: Has the query already been normalized?
+
+        Returns:
+            A pair of (score, tuple of offsets). `(0, ())` for no result.
+        """
+        default: tuple[float, Sequence[int]] = (0.0, [])
+        prefix_length = 0
+        if (strip_prefix := self.config.strip_prefix) and candidate.startswith(
+            strip_prefix
+        ):
+            # Match without the prefix, and offset positions to index the original
+            prefix_length = len(strip_prefix)
+            candidate = candidate[prefix_length:]
+        # The text the best positions were scored against, and the offset of that text
+        scored_text = candidate
+        scored_offset = 0
+        if self.config.line_aware and "\n" in candidate:
+            # Match each line separately, and pick the best
+            best_score, best_positions = default
+            line_offset = 0
+            for line in candidate.split("\n"):
+                score, positions = max(
+                    self._match(query, line, normalized=normalized),
+                    key=itemgetter(0),
+                    default=default,
+                )
+                if score > best_score:
+                    best_score = score
+                    best_positions = [position + line_offset for position in positions]
+                    scored_text = line
+                    scored_offset = line_offset
+                line_offset += len(line) + 1
+            result = (best_score, best_positions)
+        elif (
+            (chunk_size := self.config.parallel_chunk_size) is not None
+            and len(candidate) > chunk_size * 2
+            and self._can_match_in_chunks()
+        ):
+            result = self._best_match_chunked(
+                query, candidate, chunk_size, normalized
+            )
+        else:
+            result = max(
+                self._match(query, candidate, normalized=normalized),
+                key=itemgetter(0),
+                default=default,
+            )
+        acronym_multiplier = 1.0
+        if not result[0] and (acronym_fallback := self.config.acronym_fallback):
+            score, positions = self._match_acronym(query, candidate, normalized)
+            result = (score * acronym_fallback, positions)
+            acronym_multiplier = acronym_fallback
+            scored_text = candidate
+            scored_offset = 0
+        extension_boost = 1.0
+        if result[0] and self.config.extension_boost != 1.0:
+            score, positions = result
+            extension_boost = self.get_extension_boost(query, candidate)
+            result = (score * extension_boost, positions)
+        if breakdowns is not None and result[1]:
+            breakdown = self.score_breakdown(
+                scored_text, [position - scored_offset for position in result[1]]
+            )
+            breakdowns.append(
+                replace(
+                    breakdown,
+                    acronym_multiplier=acronym_multiplier,
+                    extension_boost=extension_boost,
+                )
+            )
+        if prefix_length and result[0]:
+            score, positions = result
+            result = (score, [position + prefix_length for position in positions])
+        if result[0] and result[0] < (min_match_score := self.config.min_match_score):
+            result = (min_match_score, result[1])
+        if breakdowns:
+            breakdowns[-1] = replace(breakdowns[-1], final_score=result[0])
+        return result
+
+    def _match_acronym(
+        self, query: str, candidate: str, normalized: bool = False
+    ) -> tuple[float, Sequence[int]]:
+        """Match a query against the first letters of words in a candidate.
+
+        Args:
+            query: The fuzzy query.
+            candidate: A candidate to check.
+            normalized: Has the query already been normalized?
+
+        Returns:
+            A pair of (score, tuple of offsets). `(0, ())` for no result.
+        """
+        case_sensitive = self.is_case_sensitive(query)
+        if not normalized:
+            query = self.normalize_query(query)
+        query = query.replace(" ", "")
+        if not query or not candidate:
+            return (0.0, [])
+        first_letters = self.get_first_letters(candidate)
+        # Path mode may report a boundary after a trailing separator
+        remaining_first_letters = iter(
+            sorted(position for position in first_letters if position < len(candidate))
+        )
+        positions: list[int] = []
+        for character in query:
+            for position in remaining_first_letters:
+                if (
+                    self._normalize_candidate(
+                        candidate[position], case_sensitive
+                    ).text
+                    == character
+                ):
+                    positions.append(position)
+                    break
+            else:
+                return (0.0, [])
+        return (self.score(candidate, positions, first_letters), positions)
+
+    def _best_match_chunked(
+        self, query: str, candidate: str, chunk_size: int, normalized: bool = False
+    ) -> tuple[float, Sequence[int]]:
+        """Get the best match for a long candidate, by matching chunks.
+
+        Each chunk matches the alignments whose first offset is within the chunk
+        (the rest of the alignment may extend past the end of the chunk), and scores
+        them against the whole candidate. Together the chunks cover every alignment,
+        in the same order as matching the whole candidate, so the result is the same.
+
+        Args:
+            query: The fuzzy query.
+            candidate: A candidate to check.
+            chunk_size: Number of characters between the start of each chunk.
+            normalized: Has the query already been normalized?
+
+        Returns:
+            A pair of (score, tuple of offsets). `(0, ())` for no result.
+        """
+        default: tuple[float, Sequence[int]] = (0.0, [])
+        first_letters = self.get_first_letters(candidate)
+
+        def match_chunk(start: int) -> tuple[float, Sequence[int]]:
+            """Match a single chunk (in a thread).
+
+            Args:
+                start: Offset of the start of the chunk.
+
+            Returns:
+                A pair of (score, tuple of offsets) for the chunk.
+            """
+            chunk_first_letters = frozenset(
+                offset - start for offset in first_letters if offset >= start
+            )
+
+            def score_chunk_offsets(_chunk: str, offsets: Sequence[int]) -> float:
+                """Score offsets in the chunk against the whole candidate."""
+                return self.score(
+                    candidate, [offset + start for offset in offsets], first_letters
+                )
+
+            score, positions = max(
+                self._match(
+                    query,
+                    candidate[start:],
+                    chunk_first_letters,
+                    scorer=score_chunk_offsets,
+                    normalized=normalized,
+                    first_offset_limit=chunk_size,
+                ),
+                key=itemgetter(0),
+                default=default,
+            )
+            return (score, [position + start for position in positions])
+
+        chunk_starts = range(0, len(candidate), chunk_size)
+        chunk_results: Iterable[tuple[float, Sequence[int]]] = map(
+            match_chunk, chunk_starts
+        )
+        if (executor := self._get_executor()) is not None:
+            try:
+                chunk_results = list(executor.map(match_chunk, chunk_starts))
+            except RuntimeError as error:
+                # Threads may be unavailable in restricted environments
+                logger.warning(
+                    f"Unable to match in parallel, matching serially; {error}"
+                )
+        return max(chunk_results, key=itemgetter(0), default=default)
+
+    def _can_match_in_chunks(self) -> bool:
+        """Check if the config permits matching a candidate in chunks.
+
+        Returns:
+            `True` if chunks will find the same best match as the whole candidate.
+        """
+        config = self.config
+        return not (
+            config.substring_only
+            or config.bag_match
+            or config.prefix_fast_path
+            or config.greedy
+            or config.unicode_normalize
+            or config.max_combinations is not None
+        )
+
+    def get_extension_boost(self, query: str, candidate: str) -> float:
+        """Get the boost for a query that ends with the candidate's file extension.
+
+        Args:
+            query: The fuzzy query.
+            candidate: A candidate path.
+
+        Returns:
+            `extension_boost` from the config if the extensions match, otherwise 1.
+        """
+        if (extension_match := _EXTENSION_RE.search(query)) is None:
+            return 1.0
+        extension = extension_match.group()
+        if not self.is_case_sensitive(query):
+            extension = extension.casefold()
+            candidate = candidate.casefold()
+        if candidate.endswith(extension) and (
+            len(candidate) > len(extension)
+            and candidate[-len(extension) - 1] not in "/\\"
+        ):
+            return self.config.extension_boost
+        return 1.0
+
+    def _add_recent_query(self, query: str) -> None:
+        """Record a query as the most recent.
+
+        Args:
+            query: The fuzzy query.
+        """
+        recent_queries = self._recent_queries
+        with self._state_lock:
+            if recent_queries and next(reversed(recent_queries)) == query:
+                return
+            recent_queries.pop(query, None)
+            recent_queries[query] = None
+            if len(recent_queries) > self.MAX_RECENT_QUERIES:
+                del recent_queries[next(iter(recent_queries))]
+
+    def recent_queries(self, prefix: str = "", limit: int = 10) -> list[str]:
+        """Get previous queries that start with a given prefix.
+
+        Args:
+            prefix: Prefix of queries to return.
+            limit: Maximum number of queries to return.
+
+        Returns:
+            A list of queries, most recent first.
+        """
+        with self._state_lock:
+            recent_queries = list(self._recent_queries)
+        return list(
+            islice(
+                (
+                    query
+                    for query in reversed(recent_queries)
+                    if query.startswith(prefix) and query
+                ),
+                limit,
+            )
+        )
+
+    def _add_capped_match(self, query: str, candidate: str) -> None:
+        """Record a match which reached `max_combinations`.
+
+        Args:
+            query: The normalized query.
+            candidate: The candidate.
+        """
+        capped_matches = self._capped_matches
+        with self._state_lock:
+            capped_matches.pop((query, candidate), None)
+            capped_matches[(query, candidate)] = None
+            if len(capped_matches) > self.MAX_CAPPED_MATCHES:
+                del capped_matches[next(iter(capped_matches))]
+
+    def capped_matches(self) -> list[tuple[str, str]]:
+        """Get the matches which reached `max_combinations` in the config, and so may
+        not have the best score.
+
+        Returns:
+            A list of (normalized query, candidate), oldest first.
+        """
+        with self._state_lock:
+            return list(self._capped_matches)
+
+    def clear_capped_matches(self) -> None:
+        """Clear the matches returned by [capped_matches][toad.fuzzy.FuzzySearch.capped_matches]."""
+        with self._state_lock:
+            self._capped_matches.clear()
+
+    def match_obj(self, query: str, candidate: str) -> MatchResult:
+        """Match against a query, and return a result object.
+
+        Args:
+            query: The fuzzy query.
+            candidate: A candidate to check.
+
+        Returns:
+            A match result (which is falsey for no match).
+        """
+        score, positions = self.match(query, candidate)
+        return MatchResult(score, positions)
+
+    def _limit_candidates(self, candidates: Sequence[str]) -> Sequence[str]:
+        """Apply `max_candidates` from the config to a batch.
+
+        Args:
+            candidates: Candidates in the batch.
+
+        Returns:
+            Candidates to process.
+        """
+        self._skipped_candidates = 0
+        if (
+            max_candidates := self.config.max_candidates
+        ) is not None and max_candidates < len(candidates):
+            self._skipped_candidates = len(candidates) - max_candidates
+            return candidates[:max_candidates]
+        return candidates
+
+    def _enumerate_candidates(
+        self, candidates: Iterable[str]
+    ) -> Iterator[tuple[int, str]]:
+        """Enumerate candidates in a batch, skipping duplicates if `dedup_candidates` is set.
+
+        Args:
+            candidates: Candidates in the batch.
+
+        Yields:
+            Tuples of (candidate index, candidate).
+        """
+        if not self.config.dedup_candidates:
+            yield from enumerate(candidates)
+            return
+        seen_candidates: set[str] = set()
+        for index, candidate in enumerate(candidates):
+            if candidate not in seen_candidates:
+                seen_candidates.add(candidate)
+                yield (index, candidate)
+
+    def count_above(
+        self, query: str | CompiledQuery, candidates: Sequence[str], threshold: float
+    ) -> int:
+        """Count the candidates which score above a threshold.
+
+        Args:
+            query: The fuzzy query, or a compiled query.
+            candidates: Candidates to check.
+            threshold: Minimum score (exclusive).
+
+        Returns:
+            Number of candidates scoring above `threshold`.
+        """
+        match = self.match
+        return sum(
+            1
+            for _index, candidate in self._enumerate_candidates(
+                self._limit_candidates(candidates)
+            )
+            if match(query, candidate)[0] > threshold
+        )
+
+    def match_mask(
+        self,
+        query: str | CompiledQuery,
+        candidates: Sequence[str],
+        threshold: float = 0.0,
+    ) -> list[bool]:
+        """Match a query against candidates, and get a mask of the candidates which match.
+
+        Args:
+            query: The fuzzy query, or a compiled query.
+            candidates: Candidates to check.
+            threshold: Minimum score (exclusive) for a candidate to match.
+
+        Returns:
+            A list with a boolean for each candidate, which is `True` if the candidate
+                scored above `threshold`. Candidates which aren't processed (due to
+                `max_candidates` or `dedup_candidates`) are `False`.
+        """
+        match = self.match
+        mask = [False] * len(candidates)
+        for index, candidate in self._enumerate_candidates(
+            self._limit_candidates(candidates)
+        ):
+            mask[index] = match(query, candidate)[0] > threshold
+        return mask
+
+    def match_batch_inverse(
+        self,
+        query: str | CompiledQuery,
+        candidates: Sequence[str],
+        threshold: float = 0.0,
+    ) -> list[int]:
+        """Get the candidates which don't match a query.
+
+        This is the complement of the candidates counted by
+        [count_above][toad.fuzzy.FuzzySearch.count_above].
+
+        Args:
+            query: The fuzzy query, or a compiled query.
+            candidates: Candidates to check.
+            threshold: Maximum score (inclusive) of candidates considered not to match.
+
+        Returns:
+            Indices of candidates scoring at or below `threshold`, in order.
+        """
+        match = self.match
+        return [
+            index
+            for index, candidate in self._enumerate_candidates(
+                self._limit_candidates(candidates)
+            )
+            if match(query, candidate)[0] <= threshold
+        ]
+
+    def match_batch(
+        self,
+        query: str | CompiledQuery,
+        candidates: Sequence[str],
+        k: int | None = None,
+    ) -> list[tuple[int, float, Sequence[int]]]:
+        """Match a query against many candidates.
+
+        Args:
+            query: The fuzzy query, or a compiled query.
+            candidates: Candidates to check.
+            k: Maximum number of results, or `None` for all matches. Values larger than
+                the number of candidates are permitted.
+
+        Returns:
+            A list of (candidate index, score, offsets) for matching candidates, highest score first.
+        """
+        ranked_results = self._match_batch(query, candidates, k)
+        if self.config.relative_normalize and ranked_results:
+            # Scale scores so the best match is 1.0
+            best_score = ranked_results[0][1]
+            ranked_results = [
+                (index, score / best_score, positions)
+                for index, score, positions in ranked_results
+            ]
+        return ranked_results
+
+    def _match_batch(
+        self,
+        query: str | CompiledQuery,
+        candidates: Sequence[str],
+        k: int | None = None,
+    ) -> list[tuple[int, float, Sequence[int]]]:
+        """Match a query against many candidates, without `relative_normalize`.
+
+        Args:
+            query: The fuzzy query, or a compiled query.
+            candidates: Candidates to check.
+            k: Maximum number of results, or `None` for all matches.
+
+        Returns:
+            A list of (candidate index, score, offsets) for matching candidates, highest score first.
+        """
+        _check_limit("k", k)
+        match = self.match
+        positional_bias = self.config.positional_bias
+        candidates = self._limit_candidates(candidates)
+        results: list[tuple[int, float, Sequence[int]]] = []
+        for index, candidate in self._enumerate_candidates(candidates):
+            score, positions = match(query, candidate)
+            if score:
+                results.append((index, score - positional_bias * index, positions))
+        return self._rank(results, candidates, k)
+
+    def match_file(
+        self,
+        query: str | CompiledQuery,
+        path: str | PathLike[str],
+        k: int | None = None,
+    ) -> list[tuple[int, float, Sequence[int]]]:
+        """Match a query against the lines of a (UTF-8) file.
+
+        The file is memory mapped and read a line at a time, so large files of
+        candidates don't need to be loaded in to memory.
+
+        Args:
+            query: The fuzzy query, or a compiled query.
+            path: Path to a file with a candidate on each line.
+            k: Maximum number of results, or `None` for all matches.
+
+        Returns:
+            A list of (line index, score, offsets) for matching lines, highest score first.
+        """
+        _check_limit("k", k)
+        match = self.match
+        results: list[tuple[int, float, Sequence[int]]] = []
+        matched_candidates: dict[int, str] = {}
+        with open(path, "rb") as candidates_file:
+            if not os.fstat(candidates_file.fileno()).st_size:
+                return []
+            with mmap.mmap(
+                candidates_file.fileno(), 0, access=mmap.ACCESS_READ
+            ) as candidates_map:
+                for index, line in enumerate(iter(candidates_map.readline, b"")):
+                    candidate = line.decode("utf-8", errors="replace").rstrip("\r\n")
+                    score, positions = match(query, candidate)
+                    if score:
+                        results.append((index, score, positions))
+                        matched_candidates[index] = candidate
+        return self._rank(results, matched_candidates, k)
+
+    def find_best(
+        self, query: str | CompiledQuery, candidates: Sequence[str]
+    ) -> tuple[int, float, Sequence[int]] | None:
+        """Get the best match for a query.
+
+        Every candidate is checked, since bonuses (such as `prefix_boost`) may score
+        another candidate higher than an exact match. If scores are equal, the first
+        candidate wins.
+
+        Args:
+            query: The fuzzy query, or a compiled query.
+            candidates: Candidates to check.
+
+        Returns:
+            A tuple of (candidate index, score, offsets), or `None` if no candidates match.
+        """
+        match = self.match
+        best_match: tuple[int, float, Sequence[int]] | None = None
+        best_score = 0.0
+        for index, candidate in self._enumerate_candidates(
+            self._limit_candidates(candidates)
+        ):
+            score, positions = match(query, candidate)
+            if score > best_score:
+                best_match = (index, score, positions)
+                best_score = score
+        return best_match
+
+    def perfect_score(self, query: str | CompiledQuery) -> float:
+        """Get the score of a query matched against itself (i.e. an exact match).
+
+        Args:
+            query: The fuzzy query, or a compiled query.
+
+        Returns:
+            The score of an exact match.
+        """
+        query_text = query.query if isinstance(query, CompiledQuery) else query
+        score, _positions = self._match_cached(query_text, query_text)
+        return score
+
+    def match_for_ranker(
+        self, query: str | CompiledQuery, candidate: str
+    ) -> tuple[float, Sequence[int]]:
+        """Match against a query, with the score normalized to between 0 and 1.
+
+        The score is squashed with `score / (score + perfect_score)`, where
+        `perfect_score` is the score of an exact match for the query. An exact match
+        normalizes to 0.5, and higher scores (such as those with first letter
+        boosts) stay below 1 while keeping their order. This makes the score
+        comparable with other signals (such as recency), and is the recommended
+        input for blended ranking.
+
+        Args:
+            query: The fuzzy query, or a compiled query.
+            candidate: A candidate to check.
+
+        Returns:
+            A pair of (normalized score, tuple of offsets). `(0, ())` for no result.
+        """
+        score, positions = self.match(query, candidate)
+        if not score or not (perfect_score := self.perfect_score(query)):
+            return (0.0, positions)
+        return (score / (score + perfect_score), positions)
+
+    def top_match(
+        self, query: str | CompiledQuery
+    ) -> tuple[int, float, Sequence[int]] | None:
+        """Get the best match for a query against the candidates set with
+        [set_candidates][toad.fuzzy.FuzzySearch.set_candidates].
+
+        See [find_best][toad.fuzzy.FuzzySearch.find_best].
+
+        Args:
+            query: The fuzzy query, or a compiled query.
+
+        Returns:
+            A tuple of (candidate index, score, offsets), or `None` if no candidates match.
+        """
+        return self.find_best(query, self._candidates)
+
+    def match_batch_buffer(
+        self,
+        query: str | CompiledQuery,
+        candidates: Sequence[str],
+        k: int | None = None,
+        position_cap: int = 16,
+    ) -> array[float]:
+        """Match a query against many candidates, and pack the results in to a flat buffer.
+
+        The buffer supports the buffer protocol, for consumers which read results without
+        creating Python objects. Each result occupies `3 + position_cap` doubles:
+
+        - Candidate index.
+        - Score.
+        - Number of offsets in the buffer (at most `position_cap`).
+        - `position_cap` offsets, padded with `-1`.
+
+        Results are ordered as [match_batch][toad.fuzzy.FuzzySearch.match_batch].
+
+        Args:
+            query: The fuzzy query, or a compiled query.
+            candidates: Candidates to check.
+            k: Maximum number of results, or `None` for all matches.
+            position_cap: Maximum number of offsets stored for each result.
+
+        Returns:
+            An array of doubles.
+        """
+        _check_limit("position_cap", position_cap)
+        buffer = array("d")
+        padding = [-1.0] * position_cap
+        for index, score, positions in self._match_batch(query, candidates, k):
+            positions = positions[:position_cap]
+            buffer.extend((index, score, len(positions)))
+            buffer.extend(positions)
+            buffer.extend(padding[len(positions) :])
+        return buffer
+
+    def match_metadata(
+        self, query: str | CompiledQuery, candidates: Sequence[str]
+    ) -> list[tuple[int, float, int, int, int]]:
+        """Match a query against many candidates, and get a flat record for each match.
+
+        A lightweight alternative to results with offsets, for external systems which
+        implement their own sorting. See also
+        [match_geometry][toad.fuzzy.FuzzySearch.match_geometry].
+
+        Args:
+            query: The fuzzy query, or a compiled query.
+            candidates: Candidates to check.
+
+        Returns:
+            A list of (candidate index, score, first offset, span length, group count)
+                for matching candidates, highest score first.
+        """
+        metadata: list[tuple[int, float, int, int, int]] = []
+        for index, score, positions in self._match_batch(query, candidates):
+            group_count, first_position, span_length = self._get_geometry(positions)
+            metadata.append((index, score, first_position, span_length, group_count))
+        return metadata
+
+    def match_ids(self, query: str | CompiledQuery, k: int | None = None) -> list[int]:
+        """Match a query against the candidates set with
+        [set_candidates][toad.fuzzy.FuzzySearch.set_candidates], and get just the indices.
+
+        Args:
+            query: The fuzzy query, or a compiled query.
+            k: Maximum number of results, or `None` for all matches.
+
+        Returns:
+            Indices of matching candidates, highest score first.
+        """
+        return [
+            index
+            for index, _score, _positions in self._match_batch(
+                query, self._candidates, k
+            )
+        ]
+
+    def iter_matches(
+        self, query: str | CompiledQuery, candidates: Iterable[str]
+    ) -> Iterator[tuple[int, float, Sequence[int]]]:
+        """Lazily match a query against candidates.
+
+        Candidates are matched only as results are requested, so the caller may stop early.
+        Results are in the order of the candidates, not sorted by score.
+
+        Args:
+            query: The fuzzy query, or a compiled query.
+            candidates: Candidates to check.
+
+        Yields:
+            Tuples of (candidate index, score, offsets) for matching candidates.
+        """
+        match = self.match
+        positional_bias = self.config.positional_bias
+        for index, candidate in self._enumerate_candidates(candidates):
+            score, positions = match(query, candidate)
+            if score:
+                yield (index, score - positional_bias * index, positions)
+
+    def match_to_queue(
+        self,
+        query: str | CompiledQuery,
+        candidates: Iterable[str],
+        queue: Queue[tuple[int, float, Sequence[int]]],
+        min_score: float = 0.0,
+    ) -> int:
+        """Match a query against candidates, and put results in a queue as they are found.
+
+        Intended to be called from a background thread, while another thread gets
+        results from the queue.
+
+        Args:
+            query: The fuzzy query, or a compiled query.
+            candidates: Candidates to check.
+            queue: A queue which will receive tuples of (candidate index, score, offsets).
+            min_score: Only put results scoring above this value.
+
+        Returns:
+            Number of results put in the queue.
+        """
+        put_count = 0
+        for result in self.iter_matches(query, candidates):
+            if result[1] > min_score:
+                queue.put(result)
+                put_count += 1
+        return put_count
+
+    def _rank(
+        self,
+        results: list[tuple[int, float, Sequence[int]]],
+        candidates: Sequence[str] | Mapping[int, str],
+        k: int | None,
+    ) -> list[tuple[int, float, Sequence[int]]]:
+        """Sort results, highest score first.
+
+        Args:
+            results: A list of (candidate index, score, offsets).
+            candidates: The candidates the results refer to (or a mapping of index on to candidate).
+            k: Maximum number of results, or `None` for all results.
+
+        Returns:
+            Sorted results.
+        """
+        key = self._get_rank_key(lambda result: len(candidates[result[0]]))
+        if k is None:
+            return sorted(results, key=key, reverse=True)
+        return nlargest(k, results, key=key)
+
+    def _get_rank_key(
+        self, get_candidate_length: Callable[[tuple], int]
+    ) -> Callable[[tuple], object]:
+        """Get a key function to sort results, highest score first.
+
+        Args:
+            get_candidate_length: A function which gets the length of the candidate
+                for a result (required for `prefer_shorter`).
+
+        Returns:
+            A key function for results which begin with (candidate index, score, offsets).
+        """
+        prefer_early_match = self.config.prefer_early_match
+        prefer_shorter = self.config.prefer_shorter
+        if not (prefer_early_match or prefer_shorter):
+            return itemgetter(1)
+
+        # Break ties with the first matched offset, then the length of the candidate
+        def sort_key(result: tuple) -> tuple[float, int, int]:
+            score = result[1]
+            positions = result[2]
+            return (
+                score,
+                -positions[0] if prefer_early_match and positions else 0,
+                -get_candidate_length(result) if prefer_shorter else 0,
+            )
+
+        return sort_key
+
+    def match_iter(
+        self, query: str | CompiledQuery, candidates: Iterable[str], k: int
+    ) -> list[tuple[int, float, Sequence[int]]]:
+        """Match a query against candidates from an iterable, and keep the top results.
+
+        Candidates are consumed lazily, and only the top `k` results are retained, so
+        the candidates don't need to be held in memory.
+
+        Args:
+            query: The fuzzy query, or a compiled query.
+            candidates: An iterable of candidates (such as a generator).
+            k: Maximum number of results.
+
+        Returns:
+            A list of (candidate index, score, offsets) for matching candidates, highest score first.
+        """
+        _check_limit("k", k)
+        match = self.match
+
+        def iter_results() -> Iterator[tuple[int, float, Sequence[int], int]]:
+            """Match candidates, and add the length of the candidate to results."""
+            for index, candidate in self._enumerate_candidates(candidates):
+                score, positions = match(query, candidate)
+                if score:
+                    yield (index, score, positions, len(candidate))
+
+        top_results = nlargest(k, iter_results(), key=self._get_rank_key(itemgetter(3)))
+        return [
+            (index, score, positions)
+            for index, score, positions, _length in top_results
+        ]
+
+    def rerank(
+        self,
+        query: str,
+        candidates: Sequence[str],
+        base_scores: Sequence[float],
+        fuzzy_weight: float,
+        k: int | None = None,
+    ) -> list[tuple[int, float, Sequence[int]]]:
+        """Blend fuzzy scores with existing scores (such as recency).
+
+        The final score is `base_score * (1 - fuzzy_weight) + fuzzy_score * fuzzy_weight`,
+        where the fuzzy score is normalized so that the best match is 1. If the best
+        score isn't positive (possible with `positional_bias`), fuzzy scores are 0.
+
+        Args:
+            query: The fuzzy query.
+            candidates: Candidates to check.
+            base_scores: Existing score for each candidate.
+            fuzzy_weight: Weight of the fuzzy score, between 0 and 1.
+            k: Maximum number of results, or `None` for all matches. Values larger than
+                the number of candidates are permitted.
+
+        Returns:
+            A list of (candidate index, final score, offsets) for matching candidates,
+                highest score first.
+        """
+        _check_limit("k", k)
+        if len(candidates) != len(base_scores):
+            raise ValueError("candidates and base_scores must be the same length")
+        if not 0.0 <= fuzzy_weight <= 1.0:
+            raise ValueError("fuzzy_weight must be between 0 and 1")
+        results = self._match_batch(query, candidates)
+        if not results:
+            return []
+        max_score = results[0][1]
+        base_weight = 1.0 - fuzzy_weight
+        reranked = [
+            (
+                index,
+                base_scores[index] * base_weight
+                + (score / max_score if max_score > 0 else 0.0) * fuzzy_weight,
+                positions,
+            )
+            for index, score, positions in results
+        ]
+        return self._rank(reranked, candidates, k)
+
+    def match_batch_pinned(
+        self,
+        query: str | CompiledQuery,
+        candidates: Sequence[str],
+        pinned: Sequence[bool],
+        k: int | None = None,
+    ) -> list[tuple[int, float, Sequence[int]]]:
+        """Match a query against many candidates, where pinned candidates (such as
+        favorites) are ranked above all others.
+
+        Pinned candidates must still match the query to be included in the results.
+
+        Args:
+            query: The fuzzy query, or a compiled query.
+            candidates: Candidates to check.
+            pinned: Is the corresponding candidate pinned?
+            k: Maximum number of results, or `None` for all matches. Values larger than
+                the number of candidates are permitted.
+
+        Returns:
+            A list of (candidate index, score, offsets) for matching candidates. Pinned
+                candidates are first, and results are ordered by score within each group.
+        """
+        _check_limit("k", k)
+        if len(candidates) != len(pinned):
+            raise ValueError("candidates and pinned must be the same length")
+        pinned_results: list[tuple[int, float, Sequence[int]]] = []
+        other_results: list[tuple[int, float, Sequence[int]]] = []
+        for result in self._match_batch(query, candidates):
+            (pinned_results if pinned[result[0]] else other_results).append(result)
+        return [*pinned_results, *other_results][:k]
+
+    def match_batch_dual(
+        self,
+        query: str,
+        search_texts: Sequence[str],
+        display_texts: Sequence[str],
+        k: int | None = None,
+    ) -> list[tuple[str, float, Sequence[int]]]:
+        """Match against search texts, but return offsets within the corresponding display texts.
+
+        Useful when the display text is a part of the search text, such as a filename
+        displayed from a search on the full path. Matched characters that aren't within
+        the display text are omitted from the offsets.
+
+        Args:
+            query: The fuzzy query.
+            search_texts: Candidates to check.
+            display_texts: Display text for each candidate.
+            k: Maximum number of results, or `None` for all matches. Values larger than
+                the number of candidates are permitted.
+
+        Returns:
+            A list of (display text, score, offsets in display text), highest score first.
+        """
+        if len(search_texts) != len(display_texts):
+            raise ValueError("search_texts and display_texts must be the same length")
+        results: list[tuple[str, float, Sequence[int]]] = []
+        case_sensitive = self.is_case_sensitive(query)
+        for index, score, positions in self._match_batch(query, search_texts, k):
+            search_text = search_texts[index]
+            display_text = display_texts[index]
+            if case_sensitive:
+                display_offset = search_text.rfind(display_text)
+            else:
+                display_offset = search_text.casefold().rfind(display_text.casefold())
+            if display_offset == -1:
+                display_positions: list[int] = []
+            else:
+                display_end = display_offset + len(display_text)
+                display_positions = [
+                    position - display_offset
+                    for position in positions
+                    if display_offset <= position < display_end
+                ]
+            results.append((display_text, score, display_positions))
+        return results
+
+    def match_grouped(
+        self, query: str, candidates: Sequence[str], k_per_group: int
+    ) -> list[tuple[str, list[tuple[int, float, Sequence[int]]]]]:
+        """Match a query against path candidates, grouped by top-level directory.
+
+        Args:
+            query: The fuzzy query.
+            candidates: Candidate paths.
+            k_per_group: Maximum number of results per group.
+
+        Returns:
+            A list of (top-level directory, results) where results are as returned
+                from [match_batch][toad.fuzzy.FuzzySearch.match_batch]. Groups are ordered
+                by their best score. Candidates without a directory are grouped under `""`.
+                The top-level directory is the first non-empty path component, so
+                absolute paths are grouped by the directory below the root.
+        """
+        _check_limit("k_per_group", k_per_group)
+        normalize_path_separators = self.config.normalize_path_separators
+        groups: dict[str, list[tuple[int, float, Sequence[int]]]] = {}
+        for result in self._match_batch(query, candidates):
+            candidate = candidates[result[0]]
+            if normalize_path_separators:
+                candidate = candidate.replace("\\", "/")
+            components = [component for component in candidate.split("/") if component]
+            directory = components[0] if len(components) > 1 else ""
+            group = groups.setdefault(directory, [])
+            if len(group) < k_per_group:
+                group.append(result)
+        return list(groups.items())
+
+    def match_bucketed(
+        self,
+        query: str,
+        candidates: Sequence[str],
+        buckets: Sequence[float],
+        k_per_bucket: int,
+    ) -> list[list[tuple[int, float, Sequence[int]]]]:
+        """Match a query against candidates, and partition the results by score.
+
+        Each result is placed in the bucket with the highest threshold that its score
+        meets. Results which don't meet any threshold are discarded.
+
+        Args:
+            query: The fuzzy query.
+            candidates: Candidates to check.
+            buckets: Minimum score (inclusive) for each bucket.
+            k_per_bucket: Maximum number of results per bucket.
+
+        Returns:
+            A list of results for each bucket, in the same order as `buckets`. Results
+                are as returned from [match_batch][toad.fuzzy.FuzzySearch.match_batch].
+        """
+        _check_limit("k_per_bucket", k_per_bucket)
+        bucketed_results: list[list[tuple[int, float, Sequence[int]]]] = [
+            [] for _ in buckets
+        ]
+        # Bucket indices, from the highest threshold to the lowest
+        bucket_order = sorted(
+            range(len(buckets)), key=buckets.__getitem__, reverse=True
+        )
+        for result in self._match_batch(query, candidates):
+            score = result[1]
+            for bucket_index in bucket_order:
+                if score >= buckets[bucket_index]:
+                    bucket = bucketed_results[bucket_index]
+                    if len(bucket) < k_per_bucket:
+                        bucket.append(result)
+                    break
+        return bucketed_results
+
+    def match_batch_grouped_by_score(
+        self,
+        query: str,
+        candidates: Sequence[str],
+        epsilon: float,
+        k: int | None = None,
+    ) -> list[list[tuple[int, float, Sequence[int]]]]:
+        """Match a query against candidates, and group results with effectively equal scores.
+
+        A result is added to the current group if its score is within `epsilon` of the
+        first (highest) score in the group, otherwise it starts a new group.
+
+        Args:
+            query: The fuzzy query.
+            candidates: Candidates to check.
+            epsilon: Maximum difference in score within a group.
+            k: Maximum number of results (over all groups), or `None` for all matches.
+
+        Raises:
+            ValueError: If `epsilon` is negative.
+
+        Returns:
+            A list of groups, highest scores first. Results are as returned from
+                [match_batch][toad.fuzzy.FuzzySearch.match_batch].
+        """
+        if epsilon < 0:
+            raise ValueError(f"epsilon must not be negative; got {epsilon!r}")
+        groups: list[list[tuple[int, float, Sequence[int]]]] = []
+        group_score = 0.0
+        for result in self._match_batch(query, candidates, k):
+            score = result[1]
+            if groups and group_score - score <= epsilon:
+                groups[-1].append(result)
+            else:
+                groups.append([result])
+                group_score = score
+        return groups
+
+    def match_records(
+        self,
+        query: str,
+        records: Sequence[Mapping[str, str]],
+        field_weights: Mapping[str, float],
+        k: int | None = None,
+    ) -> list[tuple[int, float, str, Sequence[int]]]:
+        """Match a query against selected fields of records.
+
+        Each field's score is multiplied by the weight of the field, and a record is
+        scored by its best field. Fields without a weight aren't matched.
+
+        Args:
+            query: The fuzzy query.
+            records: A sequence of records, mapping field name on to text.
+            field_weights: A mapping of field name on to weight.
+            k: Maximum number of results, or `None` for all matches.
+
+        Returns:
+            A list of (record index, score, field name, offsets) for matching records,
+                highest score first. The offsets refer to the best matching field.
+        """
+        _check_limit("k", k)
+        match = self.match
+        results: list[tuple[int, float, str, Sequence[int]]] = []
+        for record_index, record in enumerate(records):
+            best_result: tuple[int, float, str, Sequence[int]] | None = None
+            for field, weight in field_weights.items():
+                if (text := record.get(field)) is None:
+                    continue
+                score, positions = match(query, text)
+                score *= weight
+                if score > 0 and (best_result is None or score > best_result[1]):
+                    best_result = (record_index, score, field, positions)
+            if best_result is not None:
+                results.append(best_result)
+        if k is None:
+            return sorted(results, key=itemgetter(1), reverse=True)
+        return nlargest(k, results, key=itemgetter(1))
+
+    def match_aliased_items(
+        self, query: str, items: Sequence[Sequence[str]], k: int | None = None
+    ) -> list[tuple[int, int, float, Sequence[int]]]:
+        """Match a query against items with several aliases, scoring each item by its best alias.
+
+        Args:
+            query: The fuzzy query.
+            items: A sequence of items, where each item is a sequence of alias strings.
+            k: Maximum number of results, or `None` for all matches.
+
+        Returns:
+            A list of (item index, alias index, score, offsets) for matching items, highest
+                score first. The offsets refer to the best matching alias.
+        """
+        _check_limit("k", k)
+        match = self.match
+        results: list[tuple[int, int, float, Sequence[int]]] = []
+        for item_index, aliases in enumerate(items):
+            best_alias_index = 0
+            best_score = 0.0
+            best_positions: Sequence[int] = []
+            for alias_index, alias in enumerate(aliases):
+                score, positions = match(query, alias)
+                if score > best_score:
+                    best_alias_index = alias_index
+                    best_score = score
+                    best_positions = positions
+            if best_score:
+                results.append(
+                    (item_index, best_alias_index, best_score, best_positions)
+                )
+        if k is None:
+            return sorted(results, key=itemgetter(2), reverse=True)
+        return nlargest(k, results, key=itemgetter(2))
+
+    def match_line(
+        self, query: str, candidate: str
+    ) -> tuple[float, Sequence[int], int | None]:
+        """Match against a multi-line candidate, and report the line of the match.
+
+        Set `line_aware` in the config to prevent matches spanning lines.
+
+        Args:
+            query: The fuzzy query.
+            candidate: A candidate to check.
+
+        Returns:
+            A tuple of (score, offsets, line index). The line index is `None` for no match.
+        """
+        score, positions = self.match(query, candidate)
+        if not score:
+            return (score, positions, None)
+        if not positions:
+            # An empty query matching an empty candidate
+            return (score, positions, 0)
+        return (score, positions, candidate.count("\n", 0, positions[0]))
+
+    def match_lcs(self, query: str, candidate: str) -> tuple[float, Sequence[int]]:
+        """Match against a query, scoring by the tightest alignment of the query.
+
+        This is an alternative to the default scoring. Every character in the query
+        must be present in order (i.e. the longest common subsequence is the full
+        query), and the score is higher the smaller the span of the matched characters.
+
+        Args:
+            query: The fuzzy query.
+            candidate: A candidate to check.
+
+        Returns:
+            A pair of (score, tuple of offsets). `(0, ())` for no result.
+        """
+        normalization = self._normalize_candidate(
+            candidate, self.is_case_sensitive(query)
+        )
+        candidate = normalization.text
+        query = self.normalize_query(query)
+        query_length = len(query)
+        candidate_length = len(candidate)
+        if not query_length or query_length > candidate_length:
+            return (0.0, ())
+
+        best_positions: list[int] = []
+        best_span = candidate_length + 1
+        start = candidate.find(query[0])
+        while start != -1:
+            # Match forwards to find the earliest end
+            end = start
+            for character in query[1:]:
+                end = candidate.find(character, end + 1)
+                if end == -1:
+                    break
+            if end == -1:
+                break
+            # Match backwards from the end to find the tightest start
+            positions = [end]
+            for character in reversed(query[:-1]):
+                positions.append(candidate.rfind(character, 0, positions[-1]))
+            positions.reverse()
+            span = end - positions[0] + 1
+            if span < best_span:
+                best_span = span
+                best_positions = positions
+                if span == query_length:
+                    break
+            start = candidate.find(query[0], positions[0] + 1)
+
+        if not best_positions:
+            return (0.0, ())
+        return (
+            query_length * query_length / best_span,
+            normalization.original_offsets(best_positions),
+        )
+
+    def match_bitset(self, query: str, candidate: str) -> tuple[float, int]:
+        """Match against a query, and return offsets as a bitset.
+
+        Args:
+            query: The fuzzy query.
+            candidate: A candidate to check.
+
+        Returns:
+            A pair of (score, bitset), where bit N of the bitset is set if offset N matched.
+                `(0, 0)` for no result.
+        """
+        score, positions = self.match(query, candidate)
+        bitset = 0
+        for position in positions:
+            bitset |= 1 << position
+        return (score, bitset)
+
+    def match_bytes(
+        self, query: str, candidate: bytes
+    ) -> tuple[float, Sequence[int]]:
+        """Match against a query, where the candidate is UTF-8 encoded bytes.
+
+        Args:
+            query: The fuzzy query.
+            candidate: A UTF-8 encoded candidate to check.
+
+        Raises:
+            ValueError: If the candidate is not valid UTF-8.
+
+        Returns:
+            A pair of (score, tuple of byte offsets). `(0, ())` for no result.
+        """
+        try:
+            candidate_text = candidate.decode("utf-8")
+        except UnicodeDecodeError as error:
+            raise ValueError(
+                f"candidate is not valid UTF-8 at byte {error.start}: {candidate!r}"
+            ) from None
+        score, positions = self.match(query, candidate_text)
+        if not positions or candidate_text.isascii():
+            return (score, positions)
+        # Convert character offsets to byte offsets
+        byte_offsets = list(
+            accumulate(
+                (len(character.encode("utf-8")) for character in candidate_text),
+                initial=0,
+            )
+        )
+        return (score, [byte_offsets[position] for position in positions])
+
+    def match_window(
+        self, query: str, candidate: str, window: int
+    ) -> tuple[float, Sequence[int]]:
+        """Match against a query, considering only the start of the candidate.
+
+        Useful for long candidates where only the beginning is visible.
+
+        Args:
+            query: The fuzzy query.
+            candidate: A candidate to check.
+            window: Number of characters from the start of the candidate to match against.
+
+        Returns:
+            A pair of (score, tuple of offsets). `(0, ())` for no result.
+        """
+        _check_limit("window", window)
+        return self.match(query, candidate[:window])
+
+    def match_with_boundaries(
+        self, query: str, candidate: str, boundaries: Iterable[int]
+    ) -> tuple[float, Sequence[int]]:
+        """Match against a query, with word boundaries supplied by the caller.
+
+        The results are not cached.
+
+        Args:
+            query: The fuzzy query.
+            candidate: A candidate to check.
+            boundaries: Offsets in the candidate which should be considered the
+                start of a word, rather than those detected automatically.
+
+        Returns:
+            A pair of (score, tuple of offsets). `(0, ())` for no result.
+        """
+        default: tuple[float, Sequence[int]] = (0.0, [])
+        return max(
+            self._match(query, candidate, frozenset(boundaries)),
+            key=itemgetter(0),
+            default=default,
+        )
+
+    def match_timed(
+        self, query: str, candidate: str
+    ) -> tuple[float, Sequence[int], dict[str, float]]:
+        """Match against a query, and report the time spent in each phase of matching.
+
+        Intended for performance tuning. Requires `profiling` in the config. The
+        results are not cached.
+
+        Args:
+            query: The fuzzy query.
+            candidate: A candidate to check.
+
+        Raises:
+            RuntimeError: If profiling is not enabled.
+
+        Returns:
+            A tuple of (score, offsets, timings), where timings maps the phases
+                "normalize", "positions", "enumeration", and "scoring" on to milliseconds.
+        """
+        if not self.config.profiling:
+            raise RuntimeError(
+                "match_timed requires profiling to be enabled in the config"
+            )
+        timings = dict.fromkeys(
+            ("normalize", "positions", "enumeration", "scoring"), 0.0
+        )
+        default: tuple[float, Sequence[int]] = (0.0, [])
+        score, positions = max(
+            self._match(query, candidate, timings=timings),
+            key=itemgetter(0),
+            default=default,
+        )
+        return (score, positions, timings)
+
+    def match_geometry(
+        self, query: str, candidate: str
+    ) -> tuple[int, int, int, Sequence[int]] | None:
+        """Match against a query, and get the shape of the best alignment.
+
+        Useful for clients which implement their own scoring.
+
+        Args:
+            query: The fuzzy query.
+            candidate: A candidate to check.
+
+        Returns:
+            A tuple of (group count, first offset, span length, offsets), or `None`
+                for no match. The group count is the number of runs of consecutive
+                matched characters, and the span length is the number of characters
+                from the first matched character to the last (inclusive).
+        """
+        _score, positions = self.match(query, candidate)
+        if not positions:
+            return None
+        return (*self._get_geometry(positions), positions)
+
+    @classmethod
+    def _get_geometry(cls, positions: Sequence[int]) -> tuple[int, int, int]:
+        """Get the shape of an alignment.
+
+        Args:
+            positions: Matched offsets (at least one).
+
+        Returns:
+            A tuple of (group count, first offset, span length).
+        """
+        group_count = 1 + sum(
+            1
+            for offset, next_offset in zip(positions, positions[1:])
+            if next_offset != offset + 1
+        )
+        first_position = positions[0]
+        span_length = positions[-1] - first_position + 1
+        return (group_count, first_position, span_length)
+
+    def match_alignment(
+        self, query: str, candidate: str
+    ) -> tuple[float, list[tuple[int, int]]]:
+        """Match against a query, and pair each query character with the candidate
+        character it matched.
+
+        Useful for highlights which connect typed characters to matched characters.
+
+        Args:
+            query: The fuzzy query.
+            candidate: A candidate to check.
 
-    def __init__(
-        self, case_sensitive: bool = False, *, cache_size: int = 1024 * 4
-    ) -> None:
-        """Initialize fuzzy search.
+        Raises:
+            ValueError: If `bag_match` is set, which matches characters in any order.
+
+        Returns:
+            A pair of (score, list of (query offset, candidate offset)). Both offsets
+                increase through the list.
+        """
+        if self.config.bag_match:
+            raise ValueError("match_alignment doesn't support bag_match")
+        score, positions = self.match(query, candidate)
+        if not positions:
+            return (score, [])
+        case_sensitive = self.is_case_sensitive(query)
+
+        def normalize_text(text: str) -> NormalizationMap:
+            """Normalize text as it is when matching.
+
+            Args:
+                text: The query or candidate.
+
+            Returns:
+                Normalized text.
+            """
+            normalization = self._normalize_candidate(text, case_sensitive)
+            if self.config.unicode_normalize:
+                normalization.normalize_unicode()
+            if ignore_chars := self.config.ignore_chars:
+                if not case_sensitive:
+                    ignore_chars = ignore_chars.casefold()
+                normalization.remove(ignore_chars)
+            return normalization
+
+        query_normalization = normalize_text(query)
+        candidate_normalization = normalize_text(candidate)
+        query_text = query_normalization.text
+        query_map = query_normalization.index_map
+        candidate_map = candidate_normalization.index_map
+        matched = set(positions)
+        alignment: dict[tuple[int, int], None] = {}
+        query_index = 0
+        previous_offset: int | None = None
+        for candidate_index, character in enumerate(candidate_normalization.text):
+            offset = (
+                candidate_index
+                if candidate_map is None
+                else candidate_map[candidate_index]
+            )
+            if offset not in matched:
+                continue
+            if offset != previous_offset:
+                # Skip query characters which weren't matched (e.g. acronym spaces)
+                while (
+                    query_index < len(query_text)
+                    and query_text[query_index] != character
+                ):
+                    query_index += 1
+                previous_offset = offset
+            if query_index == len(query_text):
+                break
+            # The rest of an expanded character (such as "ß") matches only if the next
+            # query character is the same
+            if query_text[query_index] == character:
+                query_offset = (
+                    query_index if query_map is None else query_map[query_index]
+                )
+                alignment[(query_offset, offset)] = None
+                query_index += 1
+        return (score, list(alignment))
+
+    def match_both_modes(
+        self, query: str, candidate: str
+    ) -> tuple[float, Sequence[int], float, Sequence[int]]:
+        """Match against a query, scoring the candidate as both plain text and a path.
+
+        Useful when it isn't known if a candidate is a path.
 
         Args:
-            case_sensitive: Is the match case sensitive?
-            cache_size: Number of queries to cache.
+            query: The fuzzy query.
+            candidate: A candidate to check.
+
+        Returns:
+            A tuple of (score, offsets, path score, path offsets).
         """
+        score, positions = self.match(query, candidate)
+        # Match the other mode with a temporary override, which isn't cached
+        self._check_config()
+        self._mode_override.path_mode = path_mode = not self.config.path_mode
+        try:
+            other_score, other_positions = self._best_match(
+                self.normalize_query(query), candidate, normalized=True
+            )
+        finally:
+            self._mode_override.path_mode = None
+        if (max_positions := self.config.max_positions) is not None:
+            other_positions = other_positions[:max_positions]
+        if path_mode:
+            return (score, positions, other_score, other_positions)
+        return (other_score, other_positions, score, positions)
 
-        self.case_sensitive = case_sensitive
-        self.cache: LRUCache[tuple[str, str], tuple[float, Sequence[int]]] = LRUCache(
-            cache_size
-        )
+    @property
+    def _path_mode(self) -> bool:
+        """Score candidates as paths? Follows the config unless overridden."""
+        path_mode: bool | None = getattr(self._mode_override, "path_mode", None)
+        return self.config.path_mode if path_mode is None else path_mode
 
-    def match(self, query: str, candidate: str) -> tuple[float, Sequence[int]]:
-        """Match against a query.
+    def match_weighted_chars(
+        self, query: str, candidate: str, char_weights: Sequence[float]
+    ) -> tuple[float, Sequence[int]]:
+        """Match against a query, where each character in the candidate has a weight.
+
+        The weights of matched characters are added to the score, and the alignment
+        with the highest total is chosen. The results are not cached.
 
         Args:
             query: The fuzzy query.
-            candidate: A candidate to check,.
+            candidate: A candidate to check.
+            char_weights: A weight for each character in the candidate.
+
+        Raises:
+            ValueError: If the number of weights doesn't match the length of the candidate.
 
         Returns:
             A pair of (score, tuple of offsets). `(0, ())` for no result.
         """
+        if len(char_weights) != len(candidate):
+            raise ValueError(
+                f"char_weights must have a weight for each of the {len(candidate)} "
+                f"characters in the candidate; got {len(char_weights)}"
+            )
+        default: tuple[float, Sequence[int]] = (0.0, [])
+        return max(
+            (
+                (score + sum(char_weights[offset] for offset in offsets), offsets)
+                for score, offsets in self._match(query, candidate)
+                if score
+            ),
+            key=itemgetter(0),
+            default=default,
+        )
+
+    def match_glob(self, pattern: str, candidate: str) -> tuple[float, Sequence[int]]:
+        """Match against a glob pattern, rather than a fuzzy query.
+
+        In the pattern, `*` matches any run of characters, `?` matches any single
+        character, and other characters match themselves. The pattern must match the
+        entire candidate. The results are not cached.
+
+        Args:
+            pattern: The glob pattern.
+            candidate: A candidate to check.
+
+        Returns:
+            A pair of (score, tuple of offsets). `(0, ())` for no result. The offsets
+                are those of the literal characters in the pattern. The score is between
+                0 and 1, and is higher when fewer characters are matched by wildcards.
+        """
+        if not pattern:
+            return (0.0, ())
+        case_sensitive = self.is_case_sensitive(pattern)
+        pattern = self._normalize_candidate(pattern, case_sensitive).text
+        normalization = self._normalize_candidate(candidate, case_sensitive)
+        match = _compile_glob(pattern).fullmatch(normalization.text)
+        if match is None:
+            return (0.0, ())
+        positions = normalization.original_offsets(
+            match.start(group) for group in range(1, len(match.groups()) + 1)
+        )
+        score = (len(positions) + 1) / (len(normalization.text) + 1)
+        return (score, positions)
 
-        cache_key = (query, candidate)
-        if cache_key in self.cache:
-            return self.cache[cache_key]
+    def match_tagged(
+        self, query: str, candidate: str
+    ) -> tuple[float, list[tuple[int, bool]]]:
+        """Match against a query, and tag offsets which are the start of a word (or path component).
+
+        Args:
+            query: The fuzzy query.
+            candidate: A candidate to check.
+
+        Returns:
+            A pair of (score, list of (offset, is boundary)).
+        """
+        score, positions = self.match(query, candidate)
+        first_letters = self.get_first_letters(candidate) if positions else frozenset()
+        return (
+            score,
+            [(position, position in first_letters) for position in positions],
+        )
+
+    def match_segments(
+        self, query: str, candidate: str
+    ) -> tuple[float, list[tuple[str, bool]]]:
+        """Match against a query, and split the candidate in to matched and unmatched segments.
+
+        Useful for rendering the match with markup other than Textual's.
+
+        Args:
+            query: The fuzzy query.
+            candidate: A candidate to check.
+
+        Returns:
+            A pair of (score, list of (text, is matched)). The text of the segments
+                concatenates to the candidate.
+        """
+        score, positions = self.match(query, candidate)
+        matched = set(positions)
+        segments = [
+            ("".join(character for _offset, character in group), is_matched)
+            for is_matched, group in groupby(
+                enumerate(candidate), key=lambda item: item[0] in matched
+            )
+        ]
+        return (score, segments)
+
+    def match_tier(
+        self, query: str, candidate: str
+    ) -> tuple[MatchTier, float, Sequence[int]]:
+        """Match against a query, and classify the quality of the match.
+
+        Args:
+            query: The fuzzy query.
+            candidate: A candidate to check.
+
+        Returns:
+            A tuple of (tier, score, offsets).
+        """
+        score, positions = self.match(query, candidate)
+        if not score:
+            return (MatchTier.NONE, score, positions)
+        if not positions:
+            # An empty query matching an empty candidate
+            return (MatchTier.EXACT, score, positions)
+        all_positions = positions
+        if self.config.max_positions is not None:
+            # Classify with every matched offset, not just those returned
+            _score, all_positions = self._best_match(
+                self.normalize_query(query), candidate, normalized=True
+            )
+        first_position = all_positions[0]
+        if all_positions[-1] - first_position + 1 != len(all_positions):
+            tier = MatchTier.FUZZY
+        elif first_position != 0:
+            tier = MatchTier.SUBSTRING
+        elif len(all_positions) == len(candidate):
+            tier = MatchTier.EXACT
+        else:
+            tier = MatchTier.PREFIX
+        return (tier, score, positions)
+
+    def match_with_aliases(
+        self,
+        query: str,
+        candidate: str,
+        aliases: Mapping[str, Sequence[str]],
+        *,
+        max_expansions: int = 16,
+    ) -> tuple[float, Sequence[int]]:
+        """Match against a query, where words in the query may be replaced with aliases.
+
+        For instance, with `{"rm": ["delete", "remove"]}` the query "rm" will
+        also match "delete" and "remove".
+
+        Args:
+            query: The fuzzy query.
+            candidate: A candidate to check.
+            aliases: A mapping of query words on to alternative spellings.
+            max_expansions: Maximum number of expanded queries to try.
+
+        Returns:
+            A pair of (score, tuple of offsets) for the best expansion. `(0, ())` for no result.
+        """
+        words = query.split()
+        if not words:
+            return self.match(query, candidate)
+        # Only the query as typed is a recent query, not the expansions
+        self._add_recent_query(query)
+        expanded_queries = islice(
+            (
+                " ".join(expanded_words)
+                for expanded_words in product(
+                    *[[word, *aliases.get(word, ())] for word in words]
+                )
+            ),
+            max_expansions,
+        )
         default: tuple[float, Sequence[int]] = (0.0, [])
-        result = max(self._match(query, candidate), key=itemgetter(0), default=default)
-        self.cache[cache_key] = result
-        return result
+        return max(
+            (
+                self._match_cached(expanded_query, candidate)
+                for expanded_query in expanded_queries
+            ),
+            key=itemgetter(0),
+            default=default,
+        )
+
+    def get_first_letters(self, candidate: str) -> frozenset[int]:
+        """Get the offsets of the first letter of each word (or path component).
+
+        Args:
+            candidate: The candidate string.
+
+        Returns:
+            A set of offsets.
+        """
+        self._check_config()
+        path_mode = self._path_mode
+        if path_mode == self.config.path_mode and (
+            (first_letters := self._candidate_first_letters.get(candidate)) is not None
+        ):
+            return first_letters
+        return self._first_letters_functions[path_mode](candidate)
+
+    def _resolve_first_letters_functions(
+        self,
+    ) -> tuple[Callable[[str], frozenset[int]], Callable[[str], frozenset[int]]]:
+        """Get the functions which find first letters according to the current config.
+
+        These are resolved once for each config generation, rather than for each
+        candidate.
+
+        Returns:
+            A pair of functions which take a candidate and return a set of offsets,
+                for plain text and path mode.
+        """
+        return (
+            self._resolve_first_letters(path_mode=False),
+            self._resolve_first_letters(path_mode=True),
+        )
+
+    def _resolve_first_letters(
+        self, path_mode: bool
+    ) -> Callable[[str], frozenset[int]]:
+        """Get a function which finds first letters according to the current config.
+
+        Args:
+            path_mode: Find the start of path components?
+
+        Returns:
+            A function which takes a candidate and returns a set of offsets.
+        """
+        config = self.config
+        camel_case_boundaries = config.camel_case_boundaries
+        if path_mode:
+            normalize_path_separators = config.normalize_path_separators
+            skip_leading_separator = config.skip_leading_separator
+            get_path_first_letters = (
+                self.get_first_letters_path_collapsed
+                if config.collapse_separators
+                else self.get_first_letters_path
+            )
+
+            def get_first_letters(candidate: str) -> frozenset[int]:
+                if normalize_path_separators:
+                    candidate = candidate.replace("\\", "/")
+                first_letters = get_path_first_letters(candidate)
+                if skip_leading_separator and candidate.startswith("/"):
+                    first_letters = self.skip_leading_separator(
+                        candidate, first_letters
+                    )
+                if camel_case_boundaries:
+                    first_letters |= self.get_camel_case_boundaries(candidate)
+                return first_letters
+
+        else:
+            get_word_first_letters = (
+                self.get_first_letters_unicode
+                if config.unicode_word_boundaries
+                else self.get_first_letters_default
+            )
+            if not camel_case_boundaries:
+                return get_word_first_letters
+
+            def get_first_letters(candidate: str) -> frozenset[int]:
+                return get_word_first_letters(
+                    candidate
+                ) | self.get_camel_case_boundaries(candidate)
+
+        return get_first_letters
 
     @classmethod
     @lru_cache(maxsize=1024)
-    def get_first_letters(cls, candidate: str) -> frozenset[int]:
+    def get_first_letters_default(cls, candidate: str) -> frozenset[int]:
         return frozenset({match.start() for match in finditer(r"\w+", candidate)})
 
-    def score(self, candidate: str, positions: Sequence[int]) -> float:
+    @classmethod
+    @lru_cache(maxsize=1024)
+    def get_first_letters_unicode(cls, candidate: str) -> frozenset[int]:
+        first_letters: set[int] = set()
+        previous_kind: str | None = None
+        for index, character in enumerate(candidate):
+            kind = _get_word_character_kind(character)
+            if kind == "mark":
+                # Combining marks extend the previous character
+                continue
+            if kind == "letter":
+                if previous_kind not in ("letter", "mid_letter"):
+                    first_letters.add(index)
+            elif kind == "katakana":
+                if previous_kind != "katakana":
+                    first_letters.add(index)
+            elif kind == "ideograph":
+                # Each ideograph is a word
+                first_letters.add(index)
+            elif kind == "mid" and previous_kind == "letter":
+                # Punctuation such as an apostrophe joins letters either side
+                kind = "mid_letter"
+            previous_kind = kind
+        return frozenset(first_letters)
+
+    @classmethod
+    @lru_cache(maxsize=1024)
+    def get_first_letters_path_collapsed(cls, candidate: str) -> frozenset[int]:
+        return frozenset(
+            {
+                match.start()
+                for match in finditer(r"[^/]+", candidate)
+                if match.group() != "."
+            }
+        )
+
+    @classmethod
+    def skip_leading_separator(
+        cls, candidate: str, first_letters: frozenset[int]
+    ) -> frozenset[int]:
+        """Move the boundary at the start of an absolute path to the first character
+        after the leading separator(s).
+
+        Args:
+            candidate: A path starting with `/`.
+            first_letters: Offsets of the start of each path component.
+
+        Returns:
+            A set of offsets.
+        """
+        component_start = len(candidate) - len(candidate.lstrip("/"))
+        first_letters = frozenset(
+            offset for offset in first_letters if offset > component_start
+        )
+        if component_start < len(candidate):
+            first_letters |= {component_start}
+        return first_letters
+
+    @classmethod
+    @lru_cache(maxsize=1024)
+    def get_camel_case_boundaries(cls, candidate: str) -> frozenset[int]:
+        return frozenset(
+            {
+                index
+                for index in range(1, len(candidate))
+                if candidate[index].isupper() and candidate[index - 1].islower()
+            }
+        )
+
+    @classmethod
+    @lru_cache(maxsize=1024)
+    def get_first_letters_path(cls, candidate: str) -> frozenset[int]:
+        return frozenset(
+            {
+                0,
+                *[match.start() + 1 for match in finditer(r"/", candidate)],
+            }
+        )
+
+    def explain(self, query: str, candidate: str) -> ScoreBreakdown | None:
+        """Explain how the score for a match was arrived at.
+
+        Args:
+            query: The fuzzy query.
+            candidate: A candidate to check.
+
+        Returns:
+            A breakdown of the score for the best match, or `None` if there was no match
+                (or no characters were matched, as with an empty query and candidate).
+        """
+        breakdowns: list[ScoreBreakdown] = []
+        self._best_match(query, candidate, breakdowns)
+        return breakdowns[0] if breakdowns else None
+
+    def get_basename_start(self, candidate: str) -> int:
+        """Get the offset of the last component of a path.
+
+        Args:
+            candidate: A path.
+
+        Returns:
+            Offset of the character following the last separator, or 0 if there is no separator.
+        """
+        if self.config.normalize_path_separators:
+            return max(candidate.rfind("/"), candidate.rfind("\\")) + 1
+        return candidate.rfind("/") + 1
+
+    def score(
+        self,
+        candidate: str,
+        positions: Sequence[int],
+        first_letters: frozenset[int] | None = None,
+    ) -> float:
         """Score a search.
 
         Args:
-            search: Search object.
+            candidate: The candidate string.
+            positions: Matched offsets.
+            first_letters: Offsets of word boundaries, or `None` to detect them.
 
         Returns:
             Score.
         """
+        return self._score(candidate, positions, first_letters, self._path_mode)
 
-        first_letters = self.get_first_letters(candidate)
+    def _score(
+        self,
+        candidate: str,
+        positions: Sequence[int],
+        first_letters: frozenset[int] | None,
+        path_mode: bool,
+    ) -> float:
+        """Score a search, with the mode already resolved.
+
+        Args:
+            candidate: The candidate string.
+            positions: Matched offsets.
+            first_letters: Offsets of word boundaries, or `None` to detect them.
+            path_mode: Score the candidate as a path?
+
+        Returns:
+            Score.
+        """
+        config = self.config
+        if path_mode and config.normalize_path_separators:
+            # Same length, so offsets are unchanged
+            candidate = candidate.replace("\\", "/")
+
+        if first_letters is None:
+            first_letters = self.get_first_letters(candidate)
         # This is a heuristic, and can be tweaked for better results
         # Boost first letter matches
         offset_count = len(positions)
-        score: float = offset_count + len(first_letters.intersection(positions))
+        first_letter_matches = len(first_letters.intersection(positions))
+        score: float = offset_count + (
+            self._apply_first_letter_curve(first_letter_matches)
+            * config.first_letter_boost
+        )
+        if (acronym_boost := config.acronym_boost) and first_letter_matches > 1:
+            # Boost matches on the first letters of consecutive words
+            score += self._count_acronym_pairs(positions, first_letters) * acronym_boost
+        if interior_penalty := config.interior_penalty:
+            # Penalize matches in the middle of words, but keep the score positive
+            score = max(
+                score - (offset_count - first_letter_matches) * interior_penalty,
+                self.MIN_SCORE,
+            )
+        # Boost to favor less groups
+        normalized_groups = self._normalize_groups(positions)
+        score *= 1 + (normalized_groups * normalized_groups)
+        if positions[0] == 0:
+            score *= config.prefix_boost
+        if (token_coverage_boost := config.token_coverage_boost) and first_letters:
+            # Boost matches which touch more of the words in the candidate
+            score *= 1 + token_coverage_boost * self._get_token_coverage(
+                positions, first_letters
+            )
+        if path_mode:
+            score *= self._get_path_boost(candidate, positions)
+        if length_penalty := config.length_penalty:
+            # Penalize unmatched characters, but keep the score positive
+            excess_length = len(candidate) - (positions[-1] - positions[0] + 1)
+            score = max(score - excess_length * length_penalty, self.MIN_SCORE)
+        return score
+
+    def score_breakdown(
+        self,
+        candidate: str,
+        positions: Sequence[int],
+        first_letters: frozenset[int] | None = None,
+    ) -> ScoreBreakdown:
+        """Score a search, and return the individual components.
+
+        This is slower than [score][toad.fuzzy.FuzzySearch.score], and intended for
+        explaining a score rather than matching.
+
+        Args:
+            candidate: The candidate string.
+            positions: Matched offsets.
+            first_letters: Offsets of word boundaries, or `None` to detect them.
+
+        Returns:
+            Score breakdown.
+        """
+        config = self.config
+        path_mode = self._path_mode
+        if path_mode and config.normalize_path_separators:
+            # Same length, so offsets are unchanged
+            candidate = candidate.replace("\\", "/")
+
+        if first_letters is None:
+            first_letters = self.get_first_letters(candidate)
+        offset_count = len(positions)
+        first_letter_matches = len(first_letters.intersection(positions))
+        acronym_pairs = (
+            self._count_acronym_pairs(positions, first_letters)
+            if config.acronym_boost and first_letter_matches > 1
+            else 0
+        )
+        normalized_groups = self._normalize_groups(positions)
+        token_coverage = (
+            self._get_token_coverage(positions, first_letters)
+            if config.token_coverage_boost and first_letters
+            else 0.0
+        )
+        return ScoreBreakdown(
+            offset_count=offset_count,
+            first_letter_matches=first_letter_matches,
+            weighted_first_letter_matches=self._apply_first_letter_curve(
+                first_letter_matches
+            ),
+            first_letter_boost=config.first_letter_boost,
+            interior_matches=offset_count - first_letter_matches,
+            interior_penalty=config.interior_penalty,
+            acronym_pairs=acronym_pairs,
+            acronym_boost=config.acronym_boost,
+            group_count=self._count_groups(positions),
+            consecutive_boost=1 + (normalized_groups * normalized_groups),
+            prefix_boost=config.prefix_boost if positions[0] == 0 else 1.0,
+            token_coverage=token_coverage,
+            token_coverage_boost=config.token_coverage_boost,
+            path_boost=(
+                self._get_path_boost(candidate, positions) if path_mode else 1.0
+            ),
+            excess_length=len(candidate) - (positions[-1] - positions[0] + 1),
+            length_penalty=config.length_penalty,
+            acronym_multiplier=1.0,
+            extension_boost=1.0,
+            min_score=self.MIN_SCORE,
+            min_match_score=config.min_match_score,
+            final_score=self.score(candidate, positions, first_letters),
+        )
 
+    @staticmethod
+    def _count_groups(positions: Sequence[int]) -> int:
+        """Count the runs of consecutive offsets.
+
+        Args:
+            positions: Matched offsets.
+
+        Returns:
+            Number of groups.
+        """
         groups = 1
+        for index in range(1, len(positions)):
+            if positions[index] != positions[index - 1] + 1:
+                groups += 1
+        return groups
+
+    def _normalize_groups(self, positions: Sequence[int]) -> float:
+        """Get a measure of how few groups the offsets form, for the consecutive boost.
+
+        Args:
+            positions: Matched offsets.
+
+        Returns:
+            `1.0` for a single group, falling towards `0.0` for more groups.
+        """
+        offset_count = len(positions)
+        if (min_run_length := self.config.min_run_length) <= 1:
+            return (offset_count - (self._count_groups(positions) - 1)) / offset_count
+        # Only runs of a minimum length contribute to the boost
+        run_lengths = [1]
         last_offset, *offsets = positions
         for offset in offsets:
             if offset != last_offset + 1:
-                groups += 1
+                run_lengths.append(1)
+            else:
+                run_lengths[-1] += 1
             last_offset = offset
+        qualifying_runs = [
+            run_length for run_length in run_lengths if run_length >= min_run_length
+        ]
+        if not qualifying_runs:
+            return 0.0
+        return (sum(qualifying_runs) - (len(qualifying_runs) - 1)) / offset_count
 
-        # Boost to favor less groups
-        normalized_groups = (offset_count - (groups - 1)) / offset_count
-        score *= 1 + (normalized_groups * normalized_groups)
-        return score
+    @staticmethod
+    def _count_acronym_pairs(
+        positions: Sequence[int], first_letters: frozenset[int]
+    ) -> int:
+        """Count matches on the first letters of consecutive words.
+
+        Args:
+            positions: Matched offsets.
+            first_letters: Offsets of word boundaries.
+
+        Returns:
+            Number of pairs of matched characters at the start of consecutive words.
+        """
+        word_indices = {
+            offset: word_index
+            for word_index, offset in enumerate(sorted(first_letters))
+        }
+        acronym_pairs = 0
+        previous_word_index: int | None = None
+        for position in positions:
+            word_index = word_indices.get(position)
+            if (
+                word_index is not None
+                and previous_word_index is not None
+                and word_index == previous_word_index + 1
+            ):
+                acronym_pairs += 1
+            previous_word_index = word_index
+        return acronym_pairs
+
+    @staticmethod
+    def _get_token_coverage(
+        positions: Sequence[int], first_letters: frozenset[int]
+    ) -> float:
+        """Get the fraction of words which contain a matched character.
+
+        Args:
+            positions: Matched offsets.
+            first_letters: Offsets of word boundaries.
+
+        Returns:
+            Token coverage, between `0.0` and `1.0`.
+        """
+        token_starts = sorted(first_letters)
+        touched_tokens = {
+            token_index
+            for position in positions
+            if (token_index := bisect_right(token_starts, position) - 1) >= 0
+        }
+        return len(touched_tokens) / len(token_starts)
+
+    def _get_path_boost(self, candidate: str, positions: Sequence[int]) -> float:
+        """Get the multiplier from path mode scoring.
+
+        Args:
+            candidate: The candidate path, with normalized separators.
+            positions: Matched offsets.
+
+        Returns:
+            Multiplier for the depth penalty and matches in the last component.
+        """
+        path_boost = 1.0
+        if depth_penalty := self.config.depth_penalty:
+            # Penalize matches that start deep in the tree
+            depth = candidate.count("/", 0, positions[0])
+            path_boost /= 1 + depth_penalty * depth
+        if positions[0] > candidate.rfind("/"):
+            # Boost matches in the last component
+            path_boost *= 2
+        return path_boost
+
+    def _apply_first_letter_curve(self, first_letter_matches: int) -> float:
+        """Apply the configured curve to the number of first letter matches.
+
+        Args:
+            first_letter_matches: Number of matched characters at the start of a word.
+
+        Raises:
+            ValueError: If the curve in the config is not recognized.
+
+        Returns:
+            The weighted count.
+        """
+        curve = self.config.first_letter_curve
+        if curve == "linear" or not first_letter_matches:
+            return first_letter_matches
+        if curve == "sqrt":
+            return sqrt(first_letter_matches)
+        if curve == "log":
+            return 1 + log(first_letter_matches)
+        raise ValueError(
+            f"first_letter_curve must be 'linear', 'sqrt', or 'log'; got {curve!r}"
+        )
+
+    def _match_bag(
+        self,
+        query: str,
+        candidate: str,
+        search_candidate: str,
+        index_map: list[int] | None,
+        score: Callable[[str, Sequence[int]], float],
+    ) -> Iterable[tuple[float, Sequence[int]]]:
+        """Match the characters of the query in any order, in the smallest window.
+
+        Args:
+            query: The normalized query.
+            candidate: The original candidate (used for scoring).
+            search_candidate: The normalized candidate to search.
+            index_map: A mapping of offsets in the search candidate on to the original
+                candidate, or `None` if they are the same.
+            score: A function which scores offsets in the candidate.
+
+        Returns:
+            An iterable of (score, offsets).
+        """
+        required = Counter(query)
+        window_counts: Counter[str] = Counter()
+        missing = len(query)
+        best_window: tuple[int, int] | None = None
+        start = 0
+        for end, character in enumerate(search_candidate):
+            if character in required:
+                window_counts[character] += 1
+                if window_counts[character] <= required[character]:
+                    missing -= 1
+            while not missing:
+                # Every character is in the window, so shrink it from the start
+                if (
+                    best_window is None
+                    or end - start < best_window[1] - best_window[0]
+                ):
+                    best_window = (start, end)
+                leaving = search_candidate[start]
+                if leaving in required:
+                    window_counts[leaving] -= 1
+                    if window_counts[leaving] < required[leaving]:
+                        missing += 1
+                start += 1
+        if best_window is None:
+            yield (0.0, ())
+            return
+        window_start, window_end = best_window
+        offsets: list[int] = []
+        for offset in range(window_start, window_end + 1):
+            character = search_candidate[offset]
+            if required[character]:
+                required[character] -= 1
+                offsets.append(offset)
+        offsets = _map_offsets(index_map, offsets)
+        yield score(candidate, offsets), offsets
+
+    def _match_substring(
+        self,
+        query: str,
+        candidate: str,
+        search_candidate: str,
+        index_map: list[int] | None,
+        score: Callable[[str, Sequence[int]], float],
+        first_letters: frozenset[int] | None = None,
+    ) -> Iterable[tuple[float, Sequence[int]]]:
+        """Match the query as a contiguous substring.
+
+        Args:
+            query: The (normalized) query.
+            candidate: The original candidate, used for scoring.
+            search_candidate: The text to search.
+            index_map: Maps offsets in `search_candidate` to `candidate`, or `None`
+                if they are the same.
+            score: A function which scores offsets in the candidate.
+            first_letters: Offsets of word boundaries, or `None` to detect them.
+
+        Yields:
+            Pairs of (score, offsets).
+        """
+        if not query:
+            return
+        if self.config.anchor_first_to_boundary:
+            anchors = (
+                self.get_first_letters(candidate)
+                if first_letters is None
+                else first_letters
+            )
+        else:
+            anchors = None
+        query_length = len(query)
+        index = search_candidate.find(query)
+        while index != -1:
+            offsets = _map_offsets(index_map, range(index, index + query_length))
+            if anchors is None or offsets[0] in anchors:
+                yield score(candidate, offsets), offsets
+            index = search_candidate.find(query, index + 1)
+
+    @staticmethod
+    def _record_phase(timings: dict[str, float], phase: str, phase_start: int) -> int:
+        """Add the time elapsed in a phase of matching to a dict of timings.
+
+        Args:
+            timings: A mapping of phase name on to milliseconds.
+            phase: Name of the phase.
+            phase_start: Time the phase started, from `perf_counter_ns`.
+
+        Returns:
+            The current time, from `perf_counter_ns`.
+        """
+        now = perf_counter_ns()
+        timings[phase] = timings.get(phase, 0.0) + (now - phase_start) / 1_000_000
+        return now
+
+    @classmethod
+    def _is_word_end(
+        cls, candidate: str, offset: int, first_letters: frozenset[int]
+    ) -> bool:
+        """Check if a character is the last character of a word.
+
+        Args:
+            candidate: The candidate string.
+            offset: Offset of the character.
+            first_letters: Offsets of word boundaries.
+
+        Returns:
+            `True` if the next character starts a new word, or isn't part of a word.
+        """
+        next_offset = offset + 1
+        return (
+            next_offset >= len(candidate)
+            or next_offset in first_letters
+            or not (candidate[next_offset].isalnum() or candidate[next_offset] == "_")
+        )
 
     def _match(
-        self, query: str, candidate: str
+        self,
+        query: str,
+        candidate: str,
+        first_letters: frozenset[int] | None = None,
+        timings: dict[str, float] | None = None,
+        scorer: Callable[[str, Sequence[int]], float] | None = None,
+        normalized: bool = False,
+        first_offset_limit: int | None = None,
     ) -> Iterable[tuple[float, Sequence[int]]]:
+        if (
+            self.config.trailing_space_anchor
+            and query.endswith(" ")
+            and (anchored_query := query.rstrip(" "))
+        ):
+            # Match without the space, and keep matches which end a word
+            boundaries = (
+                self.get_first_letters(candidate)
+                if first_letters is None
+                else first_letters
+            )
+            for score, offsets in self._match(
+                anchored_query,
+                candidate,
+                first_letters,
+                timings,
+                scorer,
+                normalized,
+                first_offset_limit,
+            ):
+                if offsets and self._is_word_end(candidate, offsets[-1], boundaries):
+                    yield score, offsets
+            return
+
+        phase_start = perf_counter_ns() if timings is not None else 0
         letter_positions: list[list[int]] = []
         position = 0
 
-        if not self.case_sensitive:
-            candidate = candidate.casefold()
-            query = query.casefold()
+        case_sensitive = self.is_case_sensitive(query)
+        if not normalized:
+            query = self.normalize_query(query)
+        if not query or not candidate:
+            if not query and not candidate:
+                # An empty query matches only an empty candidate
+                yield (self.MIN_SCORE, [])
+                return
+            # Nothing to match
+            yield (0.0, ())
+            return
+        # Match against the normalized candidate, but score against the original
+        normalization = self._normalize_candidate(candidate, case_sensitive)
+
+        score: Callable[[str, Sequence[int]], float]
+        if scorer is None:
+            candidate_first_letters = first_letters
+            path_mode = self._path_mode
+
+            def score_offsets(candidate: str, offsets: Sequence[int]) -> float:
+                """Score offsets, finding first letters once for the candidate."""
+                nonlocal candidate_first_letters
+                if candidate_first_letters is None:
+                    candidate_first_letters = self.get_first_letters(candidate)
+                return self._score(
+                    candidate, offsets, candidate_first_letters, path_mode
+                )
+
+            score = score_offsets
+        else:
+            score = scorer
+
+        if self.config.match_basename_only and self._path_mode:
+            if basename_start := self.get_basename_start(normalization.text):
+                normalization.slice(basename_start)
+        if self.config.unicode_normalize:
+            normalization.normalize_unicode()
+        if ignore_chars := self.config.ignore_chars:
+            # Match against the candidate without ignored characters
+            if not case_sensitive:
+                ignore_chars = ignore_chars.casefold()
+            normalization.remove(ignore_chars)
+        search_candidate = normalization.text
+        index_map = normalization.index_map
+
+        if timings is not None:
+            phase_start = self._record_phase(timings, "normalize", phase_start)
+
+        if (
+            self.config.prefix_fast_path
+            and search_candidate.startswith(query)
+            and (
+                not self.config.anchor_first_to_boundary
+                or (index_map[0] if index_map else 0)
+                in (
+                    self.get_first_letters(candidate)
+                    if first_letters is None
+                    else first_letters
+                )
+            )
+        ):
+            # The query is a prefix, so take the contiguous match at the start
+            offsets = _map_offsets(index_map, range(len(query)))
+            yield score(candidate, offsets), offsets
+            return
+
+        if self.config.substring_only:
+            yield from self._match_substring(
+                query, candidate, search_candidate, index_map, score, first_letters
+            )
+            return
 
-        score = self.score
+        if self.config.bag_match:
+            yield from self._match_bag(
+                query, candidate, search_candidate, index_map, score
+            )
+            return
+
+        if self.config.greedy:
+            # Take the first alignment from left to right, without looking for the best
+            offsets = []
+            index = 0
+            for letter in query:
+                if (location := search_candidate.find(letter, index)) == -1:
+                    yield (0.0, ())
+                    return
+                offsets.append(location)
+                index = location + 1
+            if not offsets:
+                yield (0.0, ())
+                return
+            offsets = _map_offsets(index_map, offsets)
+            yield score(candidate, offsets), offsets
+            return
 
+        query_length = len(query)
         for offset, letter in enumerate(query):
-            last_index = len(candidate) - offset
+            # Leave room for the remaining letters of the query
+            last_index = len(search_candidate) - (query_length - offset - 1)
             positions: list[int] = []
             letter_positions.append(positions)
             index = position
-            while (location := candidate.find(letter, index)) != -1:
+            while (location := search_candidate.find(letter, index)) != -1:
                 positions.append(location)
                 index = location + 1
                 if index >= last_index:
                     break
             if not positions:
+                if timings is not None:
+                    self._record_phase(timings, "positions", phase_start)
                 yield (0.0, ())
                 return
             position = positions[0] + 1
+        if timings is not None:
+            phase_start = self._record_phase(timings, "positions", phase_start)
+
+        if self.config.anchor_first_to_boundary:
+            anchors = (
+                self.get_first_letters(candidate)
+                if first_letters is None
+                else first_letters
+            )
+            letter_positions[0] = [
+                offset
+                for offset in letter_positions[0]
+                if (offset if index_map is None else index_map[offset]) in anchors
+            ]
+            if not letter_positions[0]:
+                yield (0.0, ())
+                return
+
+        if first_offset_limit is not None:
+            # Only alignments starting before the limit (when matching in chunks)
+            letter_positions[0] = [
+                offset
+                for offset in letter_positions[0]
+                if (offset if index_map is None else index_map[offset])
+                < first_offset_limit
+            ]
+            if not letter_positions[0]:
+                yield (0.0, ())
+                return
+
+        if len(letter_positions) == 1:
+            # Single character, no need to combine offsets
+            for offset in letter_positions[0]:
+                offsets = [offset if index_map is None else index_map[offset]]
+                yield score(candidate, offsets), offsets
+            return
 
         possible_offsets: list[list[int]] = []
-        query_length = len(query)
+        max_combinations = self.config.max_combinations
+        capped = False
 
         def get_offsets(offsets: list[int], positions_index: int) -> None:
             """Recursively match offsets.
@@ -127,7 +4144,14 @@ class FuzzySearch:
                 positions_index: Index of query letter.
 
             """
+            nonlocal capped
             for offset in letter_positions[positions_index]:
+                if (
+                    max_combinations is not None
+                    and len(possible_offsets) >= max_combinations
+                ):
+                    capped = True
+                    return
                 if not offsets or offset > offsets[-1]:
                     new_offsets = [*offsets, offset]
                     if len(new_offsets) == query_length:
@@ -136,5 +4160,34 @@ class FuzzySearch:
                         get_offsets(new_offsets, positions_index + 1)
 
         get_offsets([], 0)
+        if capped:
+            self._add_capped_match(query, candidate)
+        if self.config.contiguous_digits:
+            digit_runs = [
+                (match.start(), match.end() - 1)
+                for match in finditer(r"\d{2,}", query)
+            ]
+            if digit_runs:
+                # Discard offsets where digits in the query aren't matched together
+                possible_offsets = [
+                    offsets
+                    for offsets in possible_offsets
+                    if all(
+                        offsets[end] - offsets[start] == end - start
+                        for start, end in digit_runs
+                    )
+                ]
+        if index_map is not None:
+            possible_offsets = [
+                _map_offsets(index_map, offsets) for offsets in possible_offsets
+            ]
+        if timings is not None:
+            phase_start = self._record_phase(timings, "enumeration", phase_start)
+            scored = [
+                (score(candidate, offsets), offsets) for offsets in possible_offsets
+            ]
+            self._record_phase(timings, "scoring", phase_start)
+            yield from scored
+            return
         for offsets in possible_offsets:
             yield score(candidate, offsets), offsets
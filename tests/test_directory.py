@@ -0,0 +1,100 @@
+import asyncio
+from pathlib import Path
+
+import pytest
+
+from toad.directory import scan
+
+
+def make_tree(root: Path) -> None:
+    (root / "src").mkdir()
+    (root / "src" / "foo.py").write_text("")
+    (root / "src" / "bar.py").write_text("")
+    (root / "README.md").write_text("")
+
+
+def test_canonicalize_dedup(tmp_path: Path) -> None:
+    make_tree(tmp_path)
+    (tmp_path / "link").symlink_to(tmp_path / "src")
+    (tmp_path / "readme").symlink_to(tmp_path / "README.md")
+    paths = asyncio.run(scan(tmp_path))
+    assert len(paths) == 6
+    paths = asyncio.run(scan(tmp_path, canonicalize_dedup=True))
+    root = tmp_path.resolve()
+    assert sorted(path.resolve() for path in paths) == [
+        root / "README.md",
+        root / "src" / "bar.py",
+        root / "src" / "foo.py",
+    ]
+
+
+def test_sort(tmp_path: Path) -> None:
+    make_tree(tmp_path)
+    paths = asyncio.run(scan(tmp_path, sort=True, add_directories=True))
+    assert paths == [
+        tmp_path / "README.md",
+        tmp_path / "src",
+        tmp_path / "src" / "bar.py",
+        tmp_path / "src" / "foo.py",
+    ]
+
+
+def test_sort_after_timeout(tmp_path: Path) -> None:
+    make_tree(tmp_path)
+
+    async def scan_and_wait() -> tuple[list[Path], list[Path]]:
+        paths = await scan(tmp_path, sort=True, max_duration=0)
+        snapshot = list(paths)
+        # Give interrupted jobs a chance to finish
+        await asyncio.sleep(0.1)
+        return paths, snapshot
+
+    paths, snapshot = asyncio.run(scan_and_wait())
+    assert paths == snapshot == sorted(paths)
+
+
+def test_max_results(tmp_path: Path) -> None:
+    for name in "abcdefgh":
+        (tmp_path / name).mkdir()
+        (tmp_path / name / "file.txt").write_text("")
+    paths = asyncio.run(scan(tmp_path, max_results=3))
+    assert len(paths) == 3
+    assert all(path.is_relative_to(tmp_path) for path in paths)
+    assert asyncio.run(scan(tmp_path, max_results=0)) == []
+    assert len(asyncio.run(scan(tmp_path, max_results=100))) == 8
+
+
+def test_max_results_after_timeout(tmp_path: Path) -> None:
+    make_tree(tmp_path)
+
+    async def scan_and_wait() -> list[Path]:
+        paths = await scan(
+            tmp_path, max_results=1, max_duration=0, add_directories=True
+        )
+        # Give interrupted jobs a chance to finish
+        await asyncio.sleep(0.1)
+        return paths
+
+    assert len(asyncio.run(scan_and_wait())) <= 1
+
+
+def test_negative_max_results(tmp_path: Path) -> None:
+    with pytest.raises(ValueError):
+        asyncio.run(scan(tmp_path, max_results=-1))
+
+
+@pytest.mark.parametrize("max_duration", [-1.0, float("nan"), float("inf")])
+def test_invalid_max_duration(tmp_path: Path, max_duration: float) -> None:
+    with pytest.raises(ValueError, match="max_duration"):
+        asyncio.run(scan(tmp_path, max_duration=max_duration))
+
+
+def test_serial_scan(tmp_path: Path, caplog: pytest.LogCaptureFixture) -> None:
+    make_tree(tmp_path)
+    paths = asyncio.run(scan(tmp_path, max_simultaneous=0, sort=True))
+    assert paths == [
+        tmp_path / "README.md",
+        tmp_path / "src" / "bar.py",
+        tmp_path / "src" / "foo.py",
+    ]
+    assert "scanning serially" in caplog.text
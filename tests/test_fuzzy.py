@@ -0,0 +1,1671 @@
+import math
+import sys
+import threading
+from dataclasses import replace
+from pathlib import Path
+from queue import Queue
+from typing import Iterator, Sequence
+
+import pytest
+
+from toad.fuzzy import (
+    FirstLetterCurve,
+    FuzzyConfig,
+    FuzzySearch,
+    MatchResult,
+    MatchTier,
+    diff_candidates,
+)
+
+
+def test_cache_size_change_resizes_cache() -> None:
+    fuzzy_search = FuzzySearch(cache_size=8)
+    for candidate in ["foo", "foobar", "food", "fool", "afoot", "fox"]:
+        fuzzy_search.match("fo", candidate)
+    assert len(fuzzy_search.cache) == 6
+    fuzzy_search.config.cache_size = 2
+    for candidate in ["foo", "foobar", "food", "fool", "afoot", "fox"]:
+        fuzzy_search.match("fo", candidate)
+    assert len(fuzzy_search.cache) == 2
+
+
+def test_clear_cache_keeps_grown_cache() -> None:
+    fuzzy_search = FuzzySearch(cache_size=8)
+    fuzzy_search.cache.grow(32)
+    fuzzy_search.match("fo", "foo")
+    fuzzy_search.clear_cache()
+    assert len(fuzzy_search.cache) == 0
+    fuzzy_search.config.case_sensitive = True
+    fuzzy_search.match("fo", "foo")
+    assert fuzzy_search.cache.maxsize == 32
+
+
+def test_builder_config() -> None:
+    config = FuzzyConfig().set_case_sensitive().set_path_mode().set_cache_size(16)
+    fuzzy_search = FuzzySearch.from_config(config)
+    assert fuzzy_search.case_sensitive
+    assert fuzzy_search.config.path_mode
+    assert fuzzy_search.cache.maxsize == 16
+    assert fuzzy_search.match("foo", "Foo") == (0.0, ())
+
+
+EXPLAIN_CONFIGS = [
+    FuzzyConfig(),
+    FuzzyConfig(min_match_score=50.0),
+    FuzzyConfig(acronym_fallback=0.5, substring_only=True),
+    FuzzyConfig(
+        strip_prefix="src/", path_mode=True, depth_penalty=0.5, prefix_boost=2.0
+    ),
+    FuzzyConfig(path_mode=True, extension_boost=1.5),
+    FuzzyConfig(line_aware=True, length_penalty=0.1),
+]
+
+
+@pytest.mark.parametrize("config", EXPLAIN_CONFIGS)
+def test_explain_final_score_matches_match(config: FuzzyConfig) -> None:
+    fuzzy_search = FuzzySearch.from_config(config)
+    for query, candidate in [
+        ("fb", "src/foo/bar.py"),
+        ("bar.py", "src/foo/bar.py"),
+        ("fbb", "foo bar baz"),
+        ("baz", "foo\nbar baz"),
+    ]:
+        score, positions = fuzzy_search.match(query, candidate)
+        breakdown = fuzzy_search.explain(query, candidate)
+        if not score:
+            assert breakdown is None
+            continue
+        assert breakdown is not None
+        assert breakdown.final_score == score
+        assert breakdown.offset_count == len(positions)
+
+
+def test_explain_records_adjustments() -> None:
+    fuzzy_search = FuzzySearch.from_config(
+        FuzzyConfig(acronym_fallback=0.5, substring_only=True)
+    )
+    breakdown = fuzzy_search.explain("fbb", "foo bar baz")
+    assert breakdown is not None
+    assert breakdown.acronym_multiplier == 0.5
+    assert fuzzy_search.explain("zzz", "foo bar baz") is None
+
+
+@pytest.mark.parametrize(
+    "config",
+    [
+        *EXPLAIN_CONFIGS,
+        FuzzyConfig(
+            first_letter_boost=2.0,
+            first_letter_curve="sqrt",
+            interior_penalty=0.5,
+            acronym_boost=1.5,
+            token_coverage_boost=0.5,
+            min_run_length=2,
+        ),
+        FuzzyConfig.command_palette(),
+        FuzzyConfig.file_paths(),
+    ],
+)
+def test_breakdown_recomputes_score(config: FuzzyConfig) -> None:
+    fuzzy_search = FuzzySearch.from_config(config)
+    for query, candidate in [
+        ("fb", "src/foo/bar.py"),
+        ("bar.py", "src/foo/bar.py"),
+        ("fbb", "foo bar baz"),
+        ("fobz", "foo bar baz"),
+        ("baz", "foo\nbar baz"),
+    ]:
+        breakdown = fuzzy_search.explain(query, candidate)
+        if breakdown is None:
+            continue
+        score = (
+            breakdown.offset_count
+            + breakdown.weighted_first_letter_matches * breakdown.first_letter_boost
+            + breakdown.acronym_pairs * breakdown.acronym_boost
+        )
+        if breakdown.interior_penalty:
+            score = max(
+                score - breakdown.interior_matches * breakdown.interior_penalty,
+                breakdown.min_score,
+            )
+        score *= (
+            breakdown.consecutive_boost
+            * breakdown.prefix_boost
+            * (1 + breakdown.token_coverage_boost * breakdown.token_coverage)
+            * breakdown.path_boost
+        )
+        if breakdown.length_penalty:
+            score = max(
+                score - breakdown.excess_length * breakdown.length_penalty,
+                breakdown.min_score,
+            )
+        score *= breakdown.acronym_multiplier * breakdown.extension_boost
+        score = max(score, breakdown.min_match_score)
+        assert breakdown.final_score == pytest.approx(score)
+
+
+def test_match_tier() -> None:
+    fuzzy_search = FuzzySearch()
+    assert fuzzy_search.match_tier("foo", "foo")[0] == MatchTier.EXACT
+    assert fuzzy_search.match_tier("foo", "foobar")[0] == MatchTier.PREFIX
+    assert fuzzy_search.match_tier("bar", "foobar")[0] == MatchTier.SUBSTRING
+    assert fuzzy_search.match_tier("fb", "foobar")[0] == MatchTier.FUZZY
+    assert fuzzy_search.match_tier("baz", "foobar") == (MatchTier.NONE, 0.0, ())
+
+
+def test_match_tier_ignores_max_positions() -> None:
+    fuzzy_search = FuzzySearch.from_config(FuzzyConfig(max_positions=2))
+    tier, _score, positions = fuzzy_search.match_tier("foo", "foo")
+    assert tier == MatchTier.EXACT
+    assert positions == [0, 1]
+
+
+def test_recent_queries() -> None:
+    fuzzy_search = FuzzySearch()
+    for query in ["f", "fo", "foo", "bar", "fo"]:
+        fuzzy_search.match(query, "foobar")
+    assert fuzzy_search.recent_queries() == ["fo", "bar", "foo", "f"]
+    assert fuzzy_search.recent_queries("fo") == ["fo", "foo"]
+    assert fuzzy_search.recent_queries(limit=1) == ["fo"]
+
+
+def test_match_with_aliases_records_typed_query() -> None:
+    fuzzy_search = FuzzySearch()
+    score, positions = fuzzy_search.match_with_aliases(
+        "rm", "remove file", {"rm": ["remove", "delete"]}
+    )
+    assert score
+    assert positions == [0, 1, 2, 3, 4, 5]
+    assert fuzzy_search.recent_queries() == ["rm"]
+
+
+def test_negative_limits_raise() -> None:
+    fuzzy_search = FuzzySearch()
+    with pytest.raises(ValueError):
+        fuzzy_search.match_batch("foo", ["foo"], k=-1)
+    with pytest.raises(ValueError):
+        fuzzy_search.match_iter("foo", ["foo"], k=-1)
+
+
+@pytest.mark.parametrize(
+    "field",
+    [
+        "cache_size",
+        "max_queries",
+        "max_positions",
+        "max_candidates",
+    ],
+)
+def test_negative_config_limits_raise(field: str) -> None:
+    with pytest.raises(ValueError, match=field):
+        FuzzyConfig(**{field: -1})
+    config = FuzzyConfig()
+    with pytest.raises(ValueError, match=field):
+        setattr(config, field, -1)
+
+
+@pytest.mark.parametrize("field", ["max_combinations", "parallel_chunk_size"])
+def test_config_limits_must_be_positive(field: str) -> None:
+    for value in [0, -1]:
+        with pytest.raises(ValueError, match=field):
+            FuzzyConfig(**{field: value})
+    config = FuzzyConfig()
+    with pytest.raises(ValueError, match=field):
+        setattr(config, field, 0)
+    assert getattr(FuzzyConfig(**{field: None}), field) is None
+
+
+@pytest.mark.parametrize(
+    "config",
+    [
+        FuzzyConfig(),
+        FuzzyConfig.command_palette(),
+        FuzzyConfig(length_penalty=0.01),
+        FuzzyConfig(path_mode=True, depth_penalty=0.5),
+    ],
+)
+def test_chunked_match_agrees_with_serial(config: FuzzyConfig) -> None:
+    candidate = "/".join(["qqq"] * 40) + "/FooBar.py"
+    serial_search = FuzzySearch.from_config(config)
+    chunked_search = FuzzySearch.from_config(
+        replace(config, parallel_chunk_size=32, thread_count=2)
+    )
+    for query in ["foo", "fb", "ar.p", "bar.py"]:
+        assert chunked_search.match(query, candidate) == serial_search.match(
+            query, candidate
+        )
+        assert chunked_search.explain(query, candidate) == serial_search.explain(
+            query, candidate
+        )
+    chunked_search.close()
+
+
+def test_chunked_match_spans_chunks() -> None:
+    candidate = "a" + "x" * 1000 + "b"
+    chunked_search = FuzzySearch.from_config(FuzzyConfig(parallel_chunk_size=100))
+    assert chunked_search.match("ab", candidate) == FuzzySearch().match("ab", candidate)
+    assert chunked_search.match("ab", candidate) == (3.75, [0, 1001])
+    # The best alignment starts in a later chunk than the first alignment
+    candidate = "f" + "x" * 300 + " foo"
+    assert chunked_search.match("fo", candidate) == FuzzySearch().match("fo", candidate)
+    chunked_search.close()
+
+
+def test_acronym_fallback() -> None:
+    fuzzy_search = FuzzySearch.from_config(
+        FuzzyConfig(acronym_fallback=0.5, substring_only=True)
+    )
+    score, positions = fuzzy_search.match("fbb", "foo bar baz")
+    assert score
+    assert positions == [0, 4, 8]
+    assert fuzzy_search.match("fbz", "foo bar baz")[0] == 0.0
+
+
+def test_acronym_fallback_empty_candidate() -> None:
+    fuzzy_search = FuzzySearch.from_config(
+        FuzzyConfig(path_mode=True, acronym_fallback=0.5)
+    )
+    assert fuzzy_search.match("a", "")[0] == 0.0
+    assert fuzzy_search.match("ab", "a/")[0] == 0.0
+
+
+def test_relative_normalize() -> None:
+    fuzzy_search = FuzzySearch.from_config(FuzzyConfig(relative_normalize=True))
+    results = fuzzy_search.match_batch("ab", ["ab", "a_b", "xaxb"])
+    assert results[0][1] == 1.0
+    assert all(0.0 < score <= 1.0 for _index, score, _positions in results)
+
+
+def test_relative_normalize_only_applies_to_match_batch() -> None:
+    candidates = ["ab", "a_b", "xaxb"]
+    normalized_search = FuzzySearch.from_config(FuzzyConfig(relative_normalize=True))
+    fuzzy_search = FuzzySearch()
+    assert normalized_search.match_bucketed(
+        "ab", candidates, [5.0, 1.0], 10
+    ) == fuzzy_search.match_bucketed("ab", candidates, [5.0, 1.0], 10)
+    assert normalized_search.match_metadata("ab", candidates) == (
+        fuzzy_search.match_metadata("ab", candidates)
+    )
+
+
+def test_compiled_query() -> None:
+    fuzzy_search = FuzzySearch()
+    compiled_query = fuzzy_search.compile_query("Foo")
+    assert compiled_query.normalized_query == "foo"
+    assert fuzzy_search.match(compiled_query, "foobar") == fuzzy_search.match(
+        "Foo", "foobar"
+    )
+    with pytest.raises(ValueError):
+        fuzzy_search.compile_query("   ")
+
+
+def test_compiled_query_from_another_search() -> None:
+    compiled_query = FuzzySearch().compile_query("Foo")
+    case_sensitive_search = FuzzySearch(case_sensitive=True)
+    assert case_sensitive_search.match(compiled_query, "foo")[0] == 0.0
+    assert case_sensitive_search.match(compiled_query, "Foo")[0]
+
+
+def test_compiled_query_after_config_change() -> None:
+    fuzzy_search = FuzzySearch()
+    compiled_query = fuzzy_search.compile_query("Foo")
+    fuzzy_search.config.case_sensitive = True
+    assert fuzzy_search.match(compiled_query, "foo")[0] == 0.0
+
+
+def test_compiled_query_is_not_normalized_again() -> None:
+    normalized_queries: list[str] = []
+
+    class CountingFuzzySearch(FuzzySearch):
+        def normalize_query(self, query: str) -> str:
+            normalized_queries.append(query)
+            return super().normalize_query(query)
+
+    fuzzy_search = CountingFuzzySearch()
+    compiled_query = fuzzy_search.compile_query("Foo")
+    normalized_queries.clear()
+    assert fuzzy_search.match(compiled_query, "foobar")[0]
+    assert normalized_queries == []
+
+
+def test_repeated_characters_find_best_alignment() -> None:
+    fuzzy_search = FuzzySearch(path_mode=True)
+    assert fuzzy_search.match("aa", "abaa")[1] == [2, 3]
+    assert FuzzySearch().match("aa", "abaa")[1] == [2, 3]
+
+
+def test_cache_nonmatches() -> None:
+    fuzzy_search = FuzzySearch()
+    fuzzy_search.match("zz", "foo")
+    assert len(fuzzy_search.cache) == 0
+    fuzzy_search.config.cache_nonmatches = True
+    fuzzy_search.match("zz", "foo")
+    assert len(fuzzy_search.cache) == 1
+
+
+def test_max_queries() -> None:
+    fuzzy_search = FuzzySearch.from_config(FuzzyConfig(max_queries=2))
+    for query in ["a", "b", "c"]:
+        fuzzy_search.match(query, "abc")
+        fuzzy_search.match(query, "cba")
+    assert fuzzy_search.query_count() == 2
+    assert {query for query, _candidate in fuzzy_search.cache.keys()} == {"b", "c"}
+
+
+def test_nonmatching_query_does_not_evict_cached_results() -> None:
+    fuzzy_search = FuzzySearch.from_config(FuzzyConfig(max_queries=1))
+    fuzzy_search.match("a", "a")
+    fuzzy_search.match("zz", "a")
+    assert list(fuzzy_search.cache.keys()) == [("a", "a")]
+
+
+def test_positions_refer_to_original_candidate() -> None:
+    fuzzy_search = FuzzySearch.from_config(
+        FuzzyConfig(unicode_normalize=True, ignore_chars="_")
+    )
+    candidate = "café_bar"
+    score, positions = fuzzy_search.match("caféb", candidate)
+    assert score
+    assert positions == [0, 1, 2, 3, 6]
+
+
+@pytest.mark.parametrize(
+    "config",
+    [
+        FuzzyConfig(),
+        FuzzyConfig(substring_only=True),
+        FuzzyConfig(bag_match=True),
+        FuzzyConfig(greedy=True),
+        FuzzyConfig(prefix_fast_path=True),
+    ],
+)
+def test_expanded_characters_give_unique_positions(config: FuzzyConfig) -> None:
+    fuzzy_search = FuzzySearch.from_config(config)
+    assert fuzzy_search.match("ß", "ß") == (fuzzy_search.match("ß", "ß")[0], [0])
+    for query, candidate in [("ß", "straße"), ("ss", "ß"), ("straße", "STRASSE")]:
+        _score, positions = fuzzy_search.match(query, candidate)
+        assert positions
+        assert len(set(positions)) == len(positions)
+        assert list(positions) == sorted(positions)
+
+
+@pytest.mark.parametrize(
+    "config",
+    [FuzzyConfig(), FuzzyConfig(ignore_chars="_"), FuzzyConfig(greedy=True)],
+)
+def test_match_alignment(config: FuzzyConfig) -> None:
+    fuzzy_search = FuzzySearch.from_config(config)
+    for query, candidate in [
+        ("fb", "foo bar"),
+        ("aa", "abaa"),
+        ("Straße", "strasse"),
+        ("strasse", "Straße"),
+        ("a_b", "xa_xb"),
+    ]:
+        score, alignment = fuzzy_search.match_alignment(query, candidate)
+        assert score == fuzzy_search.match(query, candidate)[0]
+        query_offsets = [query_offset for query_offset, _offset in alignment]
+        offsets = [offset for _query_offset, offset in alignment]
+        assert query_offsets == sorted(query_offsets)
+        assert offsets == sorted(offsets)
+        ignore_chars = config.ignore_chars or ""
+        assert set(query_offsets) == {
+            index
+            for index, character in enumerate(query)
+            if character not in ignore_chars
+        }
+        assert set(offsets) == set(fuzzy_search.match(query, candidate)[1])
+        for query_offset, offset in alignment:
+            assert query[query_offset].casefold()[0] == candidate[offset].casefold()[0]
+
+
+def test_match_alignment_acronym_fallback() -> None:
+    fuzzy_search = FuzzySearch.from_config(
+        FuzzyConfig(acronym_fallback=0.5, substring_only=True)
+    )
+    _score, alignment = fuzzy_search.match_alignment("f b b", "foo bar baz")
+    assert alignment == [(0, 0), (2, 4), (4, 8)]
+
+
+def test_match_alignment_rejects_bag_match() -> None:
+    fuzzy_search = FuzzySearch.from_config(FuzzyConfig(bag_match=True))
+    with pytest.raises(ValueError):
+        fuzzy_search.match_alignment("ba", "ab")
+
+
+def test_match_with_aliases() -> None:
+    fuzzy_search = FuzzySearch()
+    aliases = {"rm": ["delete", "remove"]}
+    score, positions = fuzzy_search.match_with_aliases(
+        "rm file", "delete file", aliases
+    )
+    assert score
+    assert positions == list(range(11))
+    assert fuzzy_search.match("rm file", "delete file") == (0.0, ())
+    assert fuzzy_search.match_with_aliases("rm", "foo", aliases)[0] == 0.0
+
+
+def test_ignore_chars() -> None:
+    assert FuzzySearch().match("foo_bar", "foo-bar") == (0.0, ())
+    fuzzy_search = FuzzySearch.from_config(FuzzyConfig(ignore_chars="_-"))
+    score, positions = fuzzy_search.match("foo_bar", "foo-bar")
+    assert score
+    assert positions == [0, 1, 2, 4, 5, 6]
+
+
+def test_match_obj() -> None:
+    fuzzy_search = FuzzySearch()
+    result = fuzzy_search.match_obj("fb", "foobar")
+    assert result
+    assert result == MatchResult(*fuzzy_search.match("fb", "foobar"))
+    assert result.positions == [0, 3]
+    assert not fuzzy_search.match_obj("baz", "foobar")
+
+
+def test_match_grouped() -> None:
+    fuzzy_search = FuzzySearch(path_mode=True)
+    candidates = [
+        "src/fo_o.py",
+        "tests/foo.py",
+        "foo.py",
+        "src/foo.py",
+        "src/food.py",
+        "docs/bar.md",
+    ]
+    groups = fuzzy_search.match_grouped("foo", candidates, 1)
+    assert [
+        (directory, [index for index, _score, _positions in results])
+        for directory, results in groups
+    ] == [("tests", [1]), ("", [2]), ("src", [3])]
+
+
+def test_match_grouped_absolute_paths() -> None:
+    fuzzy_search = FuzzySearch.from_config(
+        FuzzyConfig(path_mode=True, normalize_path_separators=True)
+    )
+    candidates = ["/usr/foo.py", "/home/foo.py", "C:\\src\\foo.py", "/foo.py"]
+    groups = fuzzy_search.match_grouped("foo", candidates, 2)
+    assert sorted(directory for directory, _results in groups) == [
+        "",
+        "C:",
+        "home",
+        "usr",
+    ]
+
+
+def test_match_grouped_rejects_negative_k() -> None:
+    fuzzy_search = FuzzySearch(path_mode=True)
+    with pytest.raises(ValueError):
+        fuzzy_search.match_grouped("foo", ["src/foo.py"], -1)
+
+
+def test_normalize_path_separators() -> None:
+    candidate = "C:\\src/foo\\bar.py"
+    fuzzy_search = FuzzySearch(path_mode=True)
+    assert fuzzy_search.get_first_letters(candidate) == {0, 7}
+    fuzzy_search = FuzzySearch.from_config(
+        FuzzyConfig(path_mode=True, normalize_path_separators=True)
+    )
+    assert fuzzy_search.get_first_letters(candidate) == {0, 3, 7, 11}
+    assert fuzzy_search.match("sfb", candidate)[1] == [3, 7, 11]
+
+
+def test_max_positions() -> None:
+    score, positions = FuzzySearch().match("foobar", "foobar")
+    assert positions == [0, 1, 2, 3, 4, 5]
+    fuzzy_search = FuzzySearch.from_config(FuzzyConfig(max_positions=2))
+    assert fuzzy_search.match("foobar", "foobar") == (score, [0, 1])
+    assert fuzzy_search.match_batch("foo", ["foo", "xfoo"]) == [
+        (0, 8.0, [0, 1]),
+        (1, 6.0, [1, 2]),
+    ]
+
+
+def test_match_batch_dual() -> None:
+    fuzzy_search = FuzzySearch(path_mode=True)
+    search_texts = ["src/foo.py", "lib/bar.py"]
+    score = fuzzy_search.match("sfoo", "src/foo.py")[0]
+    assert fuzzy_search.match_batch_dual(
+        "sfoo", search_texts, ["foo.py", "bar.py"]
+    ) == [("foo.py", score, [0, 1, 2])]
+    assert fuzzy_search.match_batch_dual("bar", search_texts, ["foo.py", "Baz"]) == [
+        ("Baz", 16.0, [])
+    ]
+    with pytest.raises(ValueError):
+        fuzzy_search.match_batch_dual("foo", search_texts, ["foo.py"])
+
+
+def test_match_batch_dual_smart_case() -> None:
+    fuzzy_search = FuzzySearch.from_config(FuzzyConfig(smart_case=True))
+    # The upper case query is case sensitive, so the display text is found at 0
+    assert fuzzy_search.match_batch_dual("F", ["Foo/foo"], ["Foo"]) == [
+        ("Foo", fuzzy_search.match("F", "Foo/foo")[0], [0])
+    ]
+
+
+def test_profiling() -> None:
+    fuzzy_search = FuzzySearch()
+    fuzzy_search.match("foo", "foobar")
+    assert fuzzy_search.total_candidates_processed() == 0
+    assert fuzzy_search.total_match_time_ns() == 0
+    fuzzy_search = FuzzySearch.from_config(FuzzyConfig(profiling=True))
+    fuzzy_search.match("foo", "foobar")
+    fuzzy_search.match("foo", "bar")
+    assert fuzzy_search.total_candidates_processed() == 2
+    assert fuzzy_search.total_match_time_ns() > 0
+    fuzzy_search.reset_profile()
+    assert fuzzy_search.total_candidates_processed() == 0
+    assert fuzzy_search.total_match_time_ns() == 0
+
+
+@pytest.mark.parametrize(
+    "query, candidate, expected",
+    [
+        ("bar", "foobar", (0.0, ())),
+        ("bar", "foo bar", (8.0, [4, 5, 6])),
+        ("ob", "foo bar", (0.0, ())),
+        ("fb", "xfoo bar", (0.0, ())),
+    ],
+)
+def test_anchor_first_to_boundary(
+    query: str, candidate: str, expected: tuple[float, list[int]]
+) -> None:
+    assert FuzzySearch().match(query, candidate)[0]
+    fuzzy_search = FuzzySearch.from_config(FuzzyConfig(anchor_first_to_boundary=True))
+    assert fuzzy_search.match(query, candidate) == expected
+
+
+def test_diff_candidates() -> None:
+    assert diff_candidates(["foo", "bar", "baz"], ["baz", "qux", "foo", "quux"]) == (
+        ["qux", "quux"],
+        ["bar"],
+    )
+    assert diff_candidates([], []) == ([], [])
+
+
+def test_first_letter_boost() -> None:
+    fuzzy_search = FuzzySearch()
+    assert fuzzy_search.match("b", "foo bar") == (4.0, [4])
+    assert fuzzy_search.match("fb", "foo bar") == (5.0, [0, 4])
+    fuzzy_search = FuzzySearch.from_config(FuzzyConfig(first_letter_boost=2.0))
+    assert fuzzy_search.match("b", "foo bar") == (6.0, [4])
+    assert fuzzy_search.match("a", "foo bar") == (2.0, [5])
+    assert fuzzy_search.match("fb", "foo bar") == (7.5, [0, 4])
+
+
+def test_substring_only() -> None:
+    fuzzy_search = FuzzySearch.from_config(FuzzyConfig(substring_only=True))
+    assert fuzzy_search.match("bar", "foobar") == (6.0, [3, 4, 5])
+    assert fuzzy_search.match("oob", "foobar")[1] == [1, 2, 3]
+    assert FuzzySearch().match("fb", "foobar")[0]
+    assert fuzzy_search.match("fb", "foobar")[0] == 0.0
+
+
+def test_equivalent_queries_share_cache() -> None:
+    fuzzy_search = FuzzySearch.from_config(FuzzyConfig(ignore_chars=" _"))
+    assert fuzzy_search.normalize_query("Config ") == "config"
+    fuzzy_search.match("Config", "config.toml")
+    fuzzy_search.match("config ", "config.toml")
+    fuzzy_search.match("con_fig", "config.toml")
+    assert len(fuzzy_search.cache) == 1
+    fuzzy_search = FuzzySearch(case_sensitive=True)
+    fuzzy_search.match("Config", "Config/config.toml")
+    fuzzy_search.match("config", "Config/config.toml")
+    assert len(fuzzy_search.cache) == 2
+
+
+def test_match_bitset() -> None:
+    fuzzy_search = FuzzySearch()
+    assert fuzzy_search.match_bitset("fb", "foobar") == (3.75, 0b1001)
+    assert fuzzy_search.match_bitset("baz", "foobar") == (0.0, 0)
+
+
+def test_line_aware() -> None:
+    candidate = "foo\nbar baz\nqux"
+    assert FuzzySearch().match_line("fb", candidate) == (5.0, [0, 4], 0)
+    fuzzy_search = FuzzySearch.from_config(FuzzyConfig(line_aware=True))
+    assert fuzzy_search.match_line("fb", candidate)[2] is None
+    assert fuzzy_search.match_line("bb", candidate) == (5.0, [4, 8], 1)
+    assert fuzzy_search.match_line("q", candidate) == (4.0, [12], 2)
+
+
+def test_max_candidates() -> None:
+    fuzzy_search = FuzzySearch.from_config(FuzzyConfig(max_candidates=2))
+    candidates = ["xfoo", "bar", "foo", "foo"]
+    assert fuzzy_search.match_batch("foo", candidates) == [(0, 6.0, [1, 2, 3])]
+    assert fuzzy_search.skipped_candidates == 2
+    fuzzy_search.match_batch("foo", candidates[:1])
+    assert fuzzy_search.skipped_candidates == 0
+
+
+def test_match_lcs() -> None:
+    fuzzy_search = FuzzySearch()
+    assert fuzzy_search.match_lcs("abc", "a xx abc") == (3.0, [5, 6, 7])
+    assert fuzzy_search.match_lcs("abc", "axbxc") == (1.8, [0, 2, 4])
+    assert fuzzy_search.match_lcs("ABC", "axbxc") == (1.8, [0, 2, 4])
+    assert fuzzy_search.match_lcs("abc", "ab") == (0.0, ())
+    assert fuzzy_search.match_lcs("abc", "acb") == (0.0, ())
+
+
+def test_acronym_boost() -> None:
+    fuzzy_search = FuzzySearch.from_config(FuzzyConfig(acronym_boost=2.0))
+    for query, acronym_pairs in [("fb", 1), ("fbb", 2), ("fz", 0), ("fa", 0)]:
+        breakdown = fuzzy_search.explain(query, "foo bar baz")
+        assert breakdown is not None
+        assert breakdown.acronym_pairs == acronym_pairs
+    assert FuzzySearch().match("fbb", "foo bar baz")[0] < (
+        fuzzy_search.match("fbb", "foo bar baz")[0]
+    )
+    assert FuzzySearch().match("fz", "foo bar baz") == (
+        fuzzy_search.match("fz", "foo bar baz")
+    )
+
+
+def test_camel_case_boundaries() -> None:
+    assert FuzzySearch().get_first_letters("fooBarBaz") == {0}
+    fuzzy_search = FuzzySearch.from_config(FuzzyConfig(camel_case_boundaries=True))
+    assert fuzzy_search.get_first_letters("fooBarBaz") == {0, 3, 6}
+    # Boundaries come from the original candidate, not the case folded candidate
+    breakdown = fuzzy_search.explain("fbb", "fooBarBaz")
+    assert breakdown is not None
+    assert breakdown.first_letter_matches == 3
+    assert fuzzy_search.match("fbb", "fooBarBaz")[0] > (
+        FuzzySearch().match("fbb", "fooBarBaz")[0]
+    )
+
+
+def test_first_letters_found_once_per_candidate() -> None:
+    calls: list[str] = []
+
+    class CountingFuzzySearch(FuzzySearch):
+        def get_first_letters(self, candidate: str) -> frozenset[int]:
+            calls.append(candidate)
+            return super().get_first_letters(candidate)
+
+    fuzzy_search = CountingFuzzySearch()
+    # Many alignments of the query are scored
+    assert fuzzy_search.match("ab", "a_b a_b a_b a_b")[0]
+    assert calls == ["a_b a_b a_b a_b"]
+    fuzzy_search.config.camel_case_boundaries = True
+    assert fuzzy_search.get_first_letters("fooBar") == {0, 3}
+
+
+def test_count_above() -> None:
+    fuzzy_search = FuzzySearch()
+    candidates = ["foo", "xfoo", "f_o_o", "bar"]
+    scores = [fuzzy_search.match("foo", candidate)[0] for candidate in candidates]
+    assert scores == [8.0, 6.0, pytest.approx(40 / 9), 0.0]
+    assert fuzzy_search.count_above("foo", candidates, 0) == 3
+    assert fuzzy_search.count_above("foo", candidates, 6.0) == 1
+    assert fuzzy_search.count_above("foo", candidates, 8.0) == 0
+
+
+@pytest.mark.parametrize(
+    "query, candidate",
+    [
+        ("caf\u00e9", "cafe\u0301 bar"),
+        ("cafe\u0301", "caf\u00e9 bar"),
+    ],
+)
+def test_unicode_normalize(query: str, candidate: str) -> None:
+    assert FuzzySearch().match(query, candidate) == (0.0, ())
+    fuzzy_search = FuzzySearch.from_config(FuzzyConfig(unicode_normalize=True))
+    score, positions = fuzzy_search.match(query, candidate)
+    assert score
+    assert positions == [0, 1, 2, 3]
+
+
+def test_contiguous_digits() -> None:
+    fuzzy_search = FuzzySearch.from_config(FuzzyConfig(contiguous_digits=True))
+    assert FuzzySearch().match("12", "file1_2.txt") == (2.5, [4, 6])
+    assert fuzzy_search.match("12", "file1_2.txt")[0] == 0.0
+    assert fuzzy_search.match("12", "file12.txt") == (4.0, [4, 5])
+    assert fuzzy_search.match("f12", "file1_2 file12")[1] == [0, 12, 13]
+
+
+def test_slow_match_callback() -> None:
+    slow_matches: list[tuple[str, str]] = []
+
+    def on_slow_match(query: str, candidate: str) -> None:
+        slow_matches.append((query, candidate))
+
+    config = FuzzyConfig().set_slow_match_callback(on_slow_match, 0)
+    fuzzy_search = FuzzySearch.from_config(config)
+    fuzzy_search.match("foo", "foobar")
+    fuzzy_search.match("baz", "foobar")
+    assert slow_matches == [("foo", "foobar"), ("baz", "foobar")]
+    slow_matches.clear()
+    fuzzy_search.config.slow_match_threshold_ms = 60_000
+    fuzzy_search.match("foo", "foobar")
+    assert slow_matches == []
+
+
+def test_slow_match_callback_receives_original_query() -> None:
+    slow_matches: list[tuple[str, str]] = []
+
+    def on_slow_match(query: str, candidate: str) -> None:
+        slow_matches.append((query, candidate))
+
+    config = FuzzyConfig().set_slow_match_callback(on_slow_match, 0)
+    fuzzy_search = FuzzySearch.from_config(config)
+    fuzzy_search.match(fuzzy_search.compile_query("Foo"), "foobar")
+    assert slow_matches == [("Foo", "foobar")]
+
+
+def test_match_basename_only() -> None:
+    fuzzy_search = FuzzySearch.from_config(
+        FuzzyConfig(path_mode=True, match_basename_only=True)
+    )
+    assert FuzzySearch(path_mode=True).match("src", "src/foo.py")[0]
+    assert fuzzy_search.match("src", "src/foo.py")[0] == 0.0
+    assert fuzzy_search.match("sf", "src/foo.py")[0] == 0.0
+    assert fuzzy_search.match("foo", "src/foo.py") == (16.0, [4, 5, 6])
+    assert fuzzy_search.match("foo", "foo.py") == (16.0, [0, 1, 2])
+
+
+def test_set_candidates() -> None:
+    fuzzy_search = FuzzySearch(path_mode=True)
+    fuzzy_search.set_candidates(iter(["src/foo.py", "foo-bar"]))
+    assert fuzzy_search.candidates == ["src/foo.py", "foo-bar"]
+    assert fuzzy_search.match("sf", "src/foo.py") == (
+        FuzzySearch(path_mode=True).match("sf", "src/foo.py")
+    )
+    assert fuzzy_search.get_first_letters("foo-bar") == {0}
+
+
+def test_set_candidates_follows_config_changes() -> None:
+    fuzzy_search = FuzzySearch(path_mode=True)
+    fuzzy_search.set_candidates(iter(["foo-bar"]))
+    assert fuzzy_search.get_first_letters("foo-bar") == {0}
+    fuzzy_search.config.path_mode = False
+    assert fuzzy_search.get_first_letters("foo-bar") == {0, 4}
+
+
+def test_match_tagged() -> None:
+    fuzzy_search = FuzzySearch()
+    assert fuzzy_search.match_tagged("fba", "foo bar") == (
+        fuzzy_search.match("fba", "foo bar")[0],
+        [(0, True), (4, True), (5, False)],
+    )
+    assert fuzzy_search.match_tagged("baz", "foo bar") == (0.0, [])
+
+
+def test_min_run_length() -> None:
+    fuzzy_search = FuzzySearch()
+    assert fuzzy_search.match("fobr", "foobar") == (6.25, [0, 1, 3, 5])
+    fuzzy_search = FuzzySearch.from_config(FuzzyConfig(min_run_length=3))
+    # The run of two characters no longer counts as consecutive
+    assert fuzzy_search.match("fobr", "foobar") == (5.0, [0, 1, 3, 5])
+    assert fuzzy_search.match("foob", "foobar") == (10.0, [0, 1, 2, 3])
+
+
+def test_rerank() -> None:
+    fuzzy_search = FuzzySearch()
+    candidates = ["foo", "xfoo", "bar"]
+    base_scores = [0.0, 1.0, 1.0]
+    assert fuzzy_search.rerank("foo", candidates, base_scores, 1.0) == [
+        (0, 1.0, [0, 1, 2]),
+        (1, 0.75, [1, 2, 3]),
+    ]
+    assert fuzzy_search.rerank("foo", candidates, base_scores, 0.5) == [
+        (1, 0.875, [1, 2, 3]),
+        (0, 0.5, [0, 1, 2]),
+    ]
+    assert fuzzy_search.rerank("foo", candidates, base_scores, 0.0, k=1) == [
+        (1, 1.0, [1, 2, 3])
+    ]
+    assert fuzzy_search.rerank("baz", candidates, base_scores, 0.5) == []
+    with pytest.raises(ValueError):
+        fuzzy_search.rerank("foo", candidates, base_scores, 1.5)
+    with pytest.raises(ValueError):
+        fuzzy_search.rerank("foo", candidates, base_scores[:2], 0.5)
+
+
+def test_rerank_without_positive_scores() -> None:
+    # A large positional bias makes every score negative
+    fuzzy_search = FuzzySearch.from_config(FuzzyConfig(positional_bias=10.0))
+    assert fuzzy_search.rerank("foo", ["bar", "xfoo", "foo"], [0.0, 0.0, 1.0], 0.5) == [
+        (2, 0.5, [0, 1, 2]),
+        (1, 0.0, [1, 2, 3]),
+    ]
+
+
+def test_match_with_boundaries() -> None:
+    fuzzy_search = FuzzySearch()
+    assert fuzzy_search.match("fb", "foobar") == (3.75, [0, 3])
+    assert fuzzy_search.match_with_boundaries("fb", "foobar", [0, 3]) == (5.0, [0, 3])
+    assert fuzzy_search.match_with_boundaries("fb", "foo bar", []) == (2.5, [0, 4])
+    assert fuzzy_search.match_with_boundaries("baz", "foobar", [0])[0] == 0.0
+    assert len(fuzzy_search.cache) == 1
+
+
+@pytest.mark.parametrize(
+    "candidate, first_letters",
+    [
+        ("src//foo.py", {0, 5}),
+        ("a/./b/c", {0, 4, 6}),
+        ("/src/foo", {1, 5}),
+    ],
+)
+def test_collapse_separators(candidate: str, first_letters: set[int]) -> None:
+    fuzzy_search = FuzzySearch.from_config(
+        FuzzyConfig(path_mode=True, collapse_separators=True)
+    )
+    assert fuzzy_search.get_first_letters(candidate) == first_letters
+
+
+def test_command_palette_preset() -> None:
+    fuzzy_search = FuzzySearch.for_command_palette()
+    assert fuzzy_search.config == FuzzyConfig.command_palette()
+    # Smart case
+    assert fuzzy_search.match("op", "Open File")[0]
+    assert fuzzy_search.match("Op", "open file")[0] == 0.0
+    # Prefix boost
+    assert fuzzy_search.match("op", "Open File")[0] > (
+        fuzzy_search.match("fi", "Open File")[0]
+    )
+    # Camel case boundaries
+    assert fuzzy_search.match("of", "openFile")[1] == [0, 4]
+
+
+def test_prefer_shorter() -> None:
+    candidates = ["lib/bar.py", "a/bar.py", "bar.pyc"]
+    fuzzy_search = FuzzySearch(path_mode=True)
+    results = fuzzy_search.match_batch("bar", candidates)
+    assert [index for index, _score, _positions in results] == [0, 1, 2]
+    fuzzy_search.config.prefer_shorter = True
+    results = fuzzy_search.match_batch("bar", candidates)
+    assert [index for index, _score, _positions in results] == [2, 1, 0]
+
+
+def test_file_paths_preset() -> None:
+    fuzzy_search = FuzzySearch.for_file_paths()
+    assert fuzzy_search.config == FuzzyConfig.file_paths()
+    results = fuzzy_search.match_batch(
+        "bar", ["src/foo/bar.py", "src/bar.py", "bar.py"]
+    )
+    assert [index for index, _score, _positions in results] == [2, 1, 0]
+    assert fuzzy_search.get_first_letters("src\\\\bar.py") == {0, 5}
+
+
+def test_iter_matches() -> None:
+    fuzzy_search = FuzzySearch()
+    consumed: list[str] = []
+
+    def candidates() -> Iterator[str]:
+        for candidate in ["bar", "xfoo", "foo", "foobar"]:
+            consumed.append(candidate)
+            yield candidate
+
+    matches = fuzzy_search.iter_matches("foo", candidates())
+    assert next(matches) == (1, 6.0, [1, 2, 3])
+    assert consumed == ["bar", "xfoo"]
+    assert next(matches) == (2, 8.0, [0, 1, 2])
+    assert consumed == ["bar", "xfoo", "foo"]
+
+
+@pytest.mark.parametrize(
+    "query, candidate, boost",
+    [
+        ("foo.py", "src/foo.py", 2.0),
+        ("foo.PY", "src/foo.py", 2.0),
+        ("foo.py", "src/foo.pyc", 1.0),
+        ("foo", "src/foo.py", 1.0),
+        (".py", "src/.py", 1.0),
+    ],
+)
+def test_extension_boost(query: str, candidate: str, boost: float) -> None:
+    fuzzy_search = FuzzySearch.from_config(
+        FuzzyConfig(path_mode=True, extension_boost=2.0)
+    )
+    assert fuzzy_search.get_extension_boost(query, candidate) == boost
+    assert fuzzy_search.match(query, candidate)[0] == (
+        FuzzySearch(path_mode=True).match(query, candidate)[0] * boost
+    )
+
+
+def test_match_window() -> None:
+    fuzzy_search = FuzzySearch()
+    candidate = "foo " + "x" * 100 + " bar"
+    assert fuzzy_search.match("foo", candidate)[0]
+    assert fuzzy_search.match_window("foo", candidate, 10) == (8.0, [0, 1, 2])
+    assert fuzzy_search.match_window("bar", candidate, 10)[0] == 0.0
+    assert fuzzy_search.match_window("foo", candidate, 2)[0] == 0.0
+    with pytest.raises(ValueError):
+        fuzzy_search.match_window("foo", candidate, -1)
+
+
+def test_dedup_candidates() -> None:
+    candidates = ["foo", "bar", "foo", "xfoo"]
+    assert len(FuzzySearch().match_batch("foo", candidates)) == 3
+    fuzzy_search = FuzzySearch.from_config(FuzzyConfig(dedup_candidates=True))
+    assert fuzzy_search.match_batch("foo", candidates) == [
+        (0, 8.0, [0, 1, 2]),
+        (3, 6.0, [1, 2, 3]),
+    ]
+    assert fuzzy_search.count_above("foo", candidates, 0) == 2
+
+
+def test_positional_bias() -> None:
+    candidates = ["xfoo", "bar", "foo", "foo"]
+    fuzzy_search = FuzzySearch.from_config(FuzzyConfig(positional_bias=0.5))
+    assert fuzzy_search.match_batch("foo", candidates) == [
+        (2, 7.0, [0, 1, 2]),
+        (3, 6.5, [0, 1, 2]),
+        (0, 6.0, [1, 2, 3]),
+    ]
+    fuzzy_search.config.positional_bias = 2.5
+    results = fuzzy_search.match_batch("foo", candidates)
+    assert [index for index, _score, _positions in results] == [0, 2, 3]
+
+
+def test_match_segments() -> None:
+    fuzzy_search = FuzzySearch()
+    assert fuzzy_search.match_segments("fobr", "foobar") == (
+        6.25,
+        [("fo", True), ("o", False), ("b", True), ("a", False), ("r", True)],
+    )
+    assert fuzzy_search.match_segments("baz", "foobar") == (0.0, [("foobar", False)])
+    assert fuzzy_search.match_segments("foo", "") == (0.0, [])
+
+
+def test_interior_penalty() -> None:
+    fuzzy_search = FuzzySearch.from_config(FuzzyConfig(interior_penalty=0.5))
+    assert fuzzy_search.match("fb", "foo bar") == FuzzySearch().match("fb", "foo bar")
+    assert fuzzy_search.match("fba", "foo bar")[0] < (
+        FuzzySearch().match("fba", "foo bar")[0]
+    )
+    # Heavily penalized matches are still matches
+    fuzzy_search.config.interior_penalty = 100.0
+    score, positions = fuzzy_search.match("oa", "foo bar")
+    assert 0.0 < score < 0.1
+    assert positions == [1, 5]
+
+
+def test_match_aliased_items() -> None:
+    fuzzy_search = FuzzySearch()
+    items = [["remove", "delete"], ["quit", "exit"], ["open", "edit"]]
+    assert fuzzy_search.match_aliased_items("del", items) == [(0, 1, 8.0, [0, 1, 2])]
+    # The first alias wins a tie
+    assert fuzzy_search.match_aliased_items("it", items) == [
+        (1, 0, 4.0, [2, 3]),
+        (2, 1, 4.0, [2, 3]),
+    ]
+    assert fuzzy_search.match_aliased_items("ex", items, k=1) == [(1, 1, 6.0, [0, 1])]
+    assert fuzzy_search.match_aliased_items("zz", items) == []
+
+
+def test_config_change_clears_cache() -> None:
+    fuzzy_search = FuzzySearch()
+    assert fuzzy_search.match("Foo", "foo")[0]
+    fuzzy_search.config.case_sensitive = True
+    assert fuzzy_search.match("Foo", "foo")[0] == 0.0
+    fuzzy_search.config = FuzzyConfig()
+    assert fuzzy_search.match("Foo", "foo")[0]
+    assert len(fuzzy_search.cache) == 1
+
+
+def test_greedy() -> None:
+    fuzzy_search = FuzzySearch.from_config(FuzzyConfig(greedy=True))
+    assert FuzzySearch().match("bar", "b a bar") == (8.0, [4, 5, 6])
+    assert fuzzy_search.match("bar", "b a bar")[1] == [0, 2, 6]
+    assert fuzzy_search.match("fb", "fxb foo bar")[1] == [0, 2]
+    assert fuzzy_search.match("baz", "b a bar")[0] == 0.0
+
+
+def test_match_batch_inverse() -> None:
+    fuzzy_search = FuzzySearch()
+    candidates = ["foo", "bar", "xfoo", "baz"]
+    assert fuzzy_search.match_batch_inverse("foo", candidates) == [1, 3]
+    assert fuzzy_search.match_batch_inverse("foo", candidates, 6.0) == [1, 2, 3]
+    assert fuzzy_search.count_above("foo", candidates, 6.0) == 1
+
+
+def test_prefer_early_match() -> None:
+    candidates = ["xx foo", "foo", "x foo", "xfoo"]
+    results = FuzzySearch().match_batch("foo", candidates)
+    assert [index for index, _score, _positions in results] == [0, 1, 2, 3]
+    fuzzy_search = FuzzySearch.from_config(FuzzyConfig(prefer_early_match=True))
+    results = fuzzy_search.match_batch("foo", candidates)
+    assert [index for index, _score, _positions in results] == [1, 2, 0, 3]
+
+
+def test_match_to_queue() -> None:
+    fuzzy_search = FuzzySearch()
+    queue: Queue[tuple[int, float, Sequence[int]]] = Queue()
+    candidates = ["foo", "bar", "xfoo"]
+    thread = threading.Thread(
+        target=fuzzy_search.match_to_queue, args=("foo", candidates, queue, 6.0)
+    )
+    thread.start()
+    thread.join()
+    assert queue.get_nowait() == (0, 8.0, [0, 1, 2])
+    assert queue.empty()
+    assert fuzzy_search.match_to_queue("foo", candidates, queue) == 2
+    assert [queue.get_nowait(), queue.get_nowait()] == [
+        (0, 8.0, [0, 1, 2]),
+        (2, 6.0, [1, 2, 3]),
+    ]
+
+
+@pytest.mark.parametrize(
+    "query, candidate",
+    [("hell0", "hello"), ("hello", "he110"), ("HELLO", "he110"), ("1", "l")],
+)
+def test_equivalences(query: str, candidate: str) -> None:
+    assert FuzzySearch().match(query, candidate)[0] == 0.0
+    fuzzy_search = FuzzySearch.from_config(
+        FuzzyConfig().set_equivalences([("l", "1"), ("o", "0")])
+    )
+    score, positions = fuzzy_search.match(query, candidate)
+    assert score
+    assert positions == list(range(len(candidate)))
+
+
+def test_top_match() -> None:
+    fuzzy_search = FuzzySearch()
+    assert fuzzy_search.top_match("foo") is None
+    fuzzy_search.set_candidates(["bar", "xfoo", "foo bar", "baz"])
+    assert fuzzy_search.top_match("foo") == (2, 8.0, [0, 1, 2])
+    assert fuzzy_search.top_match("qux") is None
+
+
+def test_top_match_checks_every_candidate() -> None:
+    fuzzy_search = FuzzySearch.for_command_palette()
+    fuzzy_search.set_candidates(["gs", "git status"])
+    # Bonuses score "git status" higher than the exact match
+    assert fuzzy_search.top_match("gs") == fuzzy_search.match_batch(
+        "gs", ["gs", "git status"]
+    )[0]
+    assert fuzzy_search.top_match("gs") == (1, 13.125, [0, 4])
+
+
+def test_trailing_space_anchor() -> None:
+    fuzzy_search = FuzzySearch.from_config(FuzzyConfig(trailing_space_anchor=True))
+    assert fuzzy_search.match("bar ", "foobar bar") == (8.0, [7, 8, 9])
+    assert fuzzy_search.match("foo ", "foo bar") == (8.0, [0, 1, 2])
+    assert fuzzy_search.match("foo ", "foobar")[0] == 0.0
+    assert fuzzy_search.match("foo", "foobar") == (8.0, [0, 1, 2])
+
+
+def test_trailing_space_anchor_repeated_characters() -> None:
+    fuzzy_search = FuzzySearch.from_config(FuzzyConfig(trailing_space_anchor=True))
+    assert fuzzy_search.match("foo ", "foobar foo") == (8.0, [7, 8, 9])
+    assert fuzzy_search.match("fo ", "foo") == (3.75, [0, 2])
+
+
+@pytest.mark.parametrize(
+    "curve, weighted_count",
+    [("linear", 4.0), ("sqrt", 2.0), ("log", 1 + math.log(4))],
+)
+def test_first_letter_curve(curve: FirstLetterCurve, weighted_count: float) -> None:
+    fuzzy_search = FuzzySearch.from_config(FuzzyConfig(first_letter_curve=curve))
+    # Four first letter matches, with a consecutive boost of 1.0625
+    score, positions = fuzzy_search.match("fbbq", "foo bar baz qux")
+    assert positions == [0, 4, 8, 12]
+    assert score == pytest.approx((4 + weighted_count) * 1.0625)
+    assert fuzzy_search.match("oa", "foo bar") == FuzzySearch().match("oa", "foo bar")
+
+
+def test_first_letter_curve_must_be_known() -> None:
+    fuzzy_search = FuzzySearch.from_config(
+        FuzzyConfig(first_letter_curve="cubic")  # type: ignore[arg-type]
+    )
+    with pytest.raises(ValueError, match="first_letter_curve"):
+        fuzzy_search.match("fb", "foo bar")
+
+
+def test_match_timed() -> None:
+    with pytest.raises(RuntimeError):
+        FuzzySearch().match_timed("fb", "foo bar")
+    fuzzy_search = FuzzySearch.from_config(FuzzyConfig(profiling=True))
+    score, positions, timings = fuzzy_search.match_timed("FB", "foo bar")
+    assert (score, positions) == fuzzy_search.match("fb", "foo bar")
+    assert list(timings) == ["normalize", "positions", "enumeration", "scoring"]
+    assert all(timing >= 0 for timing in timings.values())
+    assert timings["scoring"] > 0
+    assert fuzzy_search.match_timed("zz", "foo bar")[0] == 0.0
+
+
+def test_match_file(tmp_path: Path) -> None:
+    candidates = ["bar", "xfoo", "foo", "café foo"]
+    candidates_path = tmp_path / "candidates.txt"
+    candidates_path.write_text("\r\n".join(candidates), encoding="utf-8")
+    fuzzy_search = FuzzySearch()
+    assert fuzzy_search.match_file("foo", candidates_path) == (
+        fuzzy_search.match_batch("foo", candidates)
+    )
+    assert fuzzy_search.match_file("foo", str(candidates_path), k=1) == [
+        (2, 8.0, [0, 1, 2])
+    ]
+    empty_path = tmp_path / "empty.txt"
+    empty_path.write_text("")
+    assert fuzzy_search.match_file("foo", empty_path) == []
+
+
+def test_match_geometry() -> None:
+    fuzzy_search = FuzzySearch()
+    assert fuzzy_search.match_geometry("fobr", "foobar") == (3, 0, 6, [0, 1, 3, 5])
+    assert fuzzy_search.match_geometry("bar", "foo bar") == (1, 4, 3, [4, 5, 6])
+    assert fuzzy_search.match_geometry("baz", "foo bar") is None
+
+
+@pytest.mark.parametrize(
+    "query, candidate, default_score, turkish_score",
+    [
+        ("i", "İstanbul", 4.0, 4.0),
+        ("ı", "IRMAK", 0.0, 4.0),
+        ("I", "ırmak", 0.0, 4.0),
+        ("i", "I", 4.0, 0.0),
+    ],
+)
+def test_turkish_locale(
+    query: str, candidate: str, default_score: float, turkish_score: float
+) -> None:
+    assert FuzzySearch().match(query, candidate)[0] == default_score
+    fuzzy_search = FuzzySearch.from_config(FuzzyConfig(locale="tr"))
+    assert fuzzy_search.match(query, candidate)[0] == turkish_score
+
+
+def test_equivalences_in_match_lcs_and_acronym_fallback() -> None:
+    equivalences = (("l", "1"), ("o", "0"))
+    fuzzy_search = FuzzySearch.from_config(FuzzyConfig(equivalences=equivalences))
+    assert FuzzySearch().match_lcs("h3l0", "h3llo") == (0.0, ())
+    assert fuzzy_search.match_lcs("h3l0", "h3llo") == (3.2, [0, 1, 3, 4])
+    config = FuzzyConfig(acronym_fallback=0.5, substring_only=True)
+    assert FuzzySearch.from_config(config).match("1b", "load bar")[0] == 0.0
+    fuzzy_search = FuzzySearch.from_config(replace(config, equivalences=equivalences))
+    assert fuzzy_search.match("1b", "load bar") == (2.5, [0, 5])
+
+
+def test_match_bucketed() -> None:
+    fuzzy_search = FuzzySearch()
+    candidates = ["f_o_o", "foo", "xfoo", "foo bar", "bar"]
+    # Scores are 40 / 9, 8, 6, and 8
+    buckets = fuzzy_search.match_bucketed("foo", candidates, [5.0, 8.0], 1)
+    assert buckets == [[(2, 6.0, [1, 2, 3])], [(1, 8.0, [0, 1, 2])]]
+    buckets = fuzzy_search.match_bucketed("foo", candidates, [8.0, 5.0], 5)
+    assert [[index for index, _score, _positions in bucket] for bucket in buckets] == [
+        [1, 3],
+        [2],
+    ]
+    with pytest.raises(ValueError):
+        fuzzy_search.match_bucketed("foo", candidates, [5.0], -1)
+
+
+def test_strip_prefix() -> None:
+    fuzzy_search = FuzzySearch.from_config(FuzzyConfig(strip_prefix="src/"))
+    assert fuzzy_search.match("sf", "src/foo.py")[0] == 0.0
+    # Offsets still refer to the whole candidate
+    assert fuzzy_search.match("foo", "src/foo.py") == (8.0, [4, 5, 6])
+    assert fuzzy_search.match("lf", "lib/foo.py") == (5.0, [0, 4])
+    assert fuzzy_search.match("s", "src/")[0] == 0.0
+
+
+def test_match_ids() -> None:
+    fuzzy_search = FuzzySearch()
+    assert fuzzy_search.match_ids("foo") == []
+    fuzzy_search.set_candidates(["xfoo", "bar", "foo"])
+    assert fuzzy_search.match_ids("foo") == [2, 0]
+    assert fuzzy_search.match_ids("foo", k=1) == [2]
+    assert fuzzy_search.match_ids(fuzzy_search.compile_query("bar")) == [1]
+
+
+def test_match_records() -> None:
+    fuzzy_search = FuzzySearch()
+    records = [
+        {"name": "open", "description": "Open a file"},
+        {"name": "file", "description": "Show the file menu"},
+        {"name": "quit", "description": "Exit the app", "id": "file"},
+    ]
+    assert fuzzy_search.match_records(
+        "file", records, {"name": 2.0, "description": 1.0}
+    ) == [
+        (1, 20.0, "name", [0, 1, 2, 3]),
+        (0, 10.0, "description", [7, 8, 9, 10]),
+    ]
+    assert fuzzy_search.match_records("file", records, {"description": 1.0}, k=1) == [
+        (0, 10.0, "description", [7, 8, 9, 10])
+    ]
+    assert fuzzy_search.match_records("exit", records, {"name": 1.0}) == []
+
+
+def test_thread_pool_is_reused() -> None:
+    def count_threads() -> int:
+        return sum(
+            thread.name.startswith("fuzzy-search") for thread in threading.enumerate()
+        )
+
+    fuzzy_search = FuzzySearch.from_config(
+        FuzzyConfig(parallel_chunk_size=8, thread_count=2)
+    )
+    for query in ["foo", "bar", "baz", "qux"]:
+        fuzzy_search.match(query, "foo bar baz qux " * 8)
+    assert 0 < count_threads() <= 2
+    fuzzy_search.close()
+    assert count_threads() == 0
+
+
+def test_parallel_matching_is_opt_in() -> None:
+    def count_threads() -> int:
+        return sum(
+            thread.name.startswith("fuzzy-search") for thread in threading.enumerate()
+        )
+
+    fuzzy_search = FuzzySearch.from_config(FuzzyConfig(parallel_chunk_size=8))
+    assert fuzzy_search.thread_count == 1
+    fuzzy_search.match("foo", "foo bar baz qux " * 8)
+    assert count_threads() == 0
+    fuzzy_search.config.thread_count = 2
+    assert fuzzy_search.thread_count == 2
+    fuzzy_search.match_both_modes("bar", "foo bar baz qux " * 8)
+    assert count_threads() > 0
+    fuzzy_search.close()
+    assert count_threads() == 0
+
+
+@pytest.mark.parametrize(
+    "config",
+    [
+        FuzzyConfig(),
+        FuzzyConfig(path_mode=True),
+        FuzzyConfig(substring_only=True),
+        FuzzyConfig(greedy=True),
+        FuzzyConfig(acronym_fallback=0.5),
+    ],
+)
+def test_empty_query_and_candidate(config: FuzzyConfig) -> None:
+    fuzzy_search = FuzzySearch.from_config(config)
+    # An empty query matches only an empty candidate
+    assert fuzzy_search.match("", "") == (FuzzySearch.MIN_SCORE, [])
+    for query, candidate in [("foo", ""), ("", "foo"), ("f", "")]:
+        assert fuzzy_search.match(query, candidate)[0] == 0.0
+    assert fuzzy_search.match_batch("", ["", "foo"]) == [
+        (0, FuzzySearch.MIN_SCORE, [])
+    ]
+    assert fuzzy_search.explain("", "") is None
+    assert fuzzy_search.match_tier("", "")[0] == MatchTier.EXACT
+    assert fuzzy_search.match_line("", "") == (FuzzySearch.MIN_SCORE, [], 0)
+
+
+@pytest.mark.parametrize("path_mode", [False, True])
+def test_match_both_modes(path_mode: bool) -> None:
+    fuzzy_search = FuzzySearch(path_mode=path_mode)
+    candidate = "src/foo/bar.py"
+    assert fuzzy_search.match_both_modes("bar", candidate) == (
+        *FuzzySearch().match("bar", candidate),
+        *FuzzySearch(path_mode=True).match("bar", candidate),
+    )
+    # Only results for the configured mode are cached
+    assert list(fuzzy_search.cache.keys()) == [("bar", candidate)]
+    assert fuzzy_search.match("bar", candidate) == (
+        FuzzySearch(path_mode=path_mode).match("bar", candidate)
+    )
+    fuzzy_search.config.case_sensitive = True
+    assert fuzzy_search.match_both_modes("BAR", candidate) == (0.0, (), 0.0, ())
+
+
+def test_length_penalty() -> None:
+    fuzzy_search = FuzzySearch.from_config(FuzzyConfig(length_penalty=0.1))
+    assert fuzzy_search.match("foo", "foo") == (8.0, [0, 1, 2])
+    assert fuzzy_search.match("foo", "foobar") == (pytest.approx(7.7), [0, 1, 2])
+    results = fuzzy_search.match_batch("foo", ["foo bar baz", "foobar", "foo"])
+    assert [index for index, _score, _positions in results] == [2, 1, 0]
+    fuzzy_search.config.length_penalty = 100.0
+    assert fuzzy_search.match("foo", "foo bar") == (FuzzySearch.MIN_SCORE, [0, 1, 2])
+
+
+def test_match_batch_buffer() -> None:
+    fuzzy_search = FuzzySearch()
+    candidates = ["xfoo", "bar", "foobar"]
+    buffer = fuzzy_search.match_batch_buffer("foo", candidates, position_cap=4)
+    assert buffer.typecode == "d"
+    assert list(memoryview(buffer)) == [
+        *(2.0, 8.0, 3.0, 0.0, 1.0, 2.0, -1.0),
+        *(0.0, 6.0, 3.0, 1.0, 2.0, 3.0, -1.0),
+    ]
+    buffer = fuzzy_search.match_batch_buffer("foo", candidates, k=1, position_cap=2)
+    assert list(buffer) == [2.0, 8.0, 2.0, 0.0, 1.0]
+    with pytest.raises(ValueError):
+        fuzzy_search.match_batch_buffer("foo", candidates, position_cap=-1)
+
+
+@pytest.mark.parametrize(
+    "query, candidate, expected",
+    [
+        ("oof", "foo", (8.0, [0, 1, 2])),
+        ("tac", "the cat", (8.0, [4, 5, 6])),
+        ("ab", "b a", (5.0, [0, 2])),
+        ("aab", "bar", (0.0, ())),
+    ],
+)
+def test_bag_match(
+    query: str, candidate: str, expected: tuple[float, list[int]]
+) -> None:
+    fuzzy_search = FuzzySearch.from_config(FuzzyConfig(bag_match=True))
+    assert fuzzy_search.match(query, candidate) == expected
+
+
+def test_bag_match_empty() -> None:
+    fuzzy_search = FuzzySearch.from_config(FuzzyConfig(bag_match=True))
+    assert fuzzy_search.match("", "") == (FuzzySearch.MIN_SCORE, [])
+    for query, candidate in [("foo", ""), ("", "foo")]:
+        assert fuzzy_search.match(query, candidate)[0] == 0.0
+    assert fuzzy_search.match_batch("", ["", "foo"]) == [
+        (0, FuzzySearch.MIN_SCORE, [])
+    ]
+
+
+def test_match_batch_grouped_by_score() -> None:
+    fuzzy_search = FuzzySearch()
+    # Scores are 8, 6, 40 / 9, and 8
+    candidates = ["foo", "xfoo", "f_o_o", "foo bar"]
+
+    def get_indices(epsilon: float, k: int | None = None) -> list[list[int]]:
+        groups = fuzzy_search.match_batch_grouped_by_score(
+            "foo", candidates, epsilon, k
+        )
+        return [[result[0] for result in group] for group in groups]
+
+    assert get_indices(0.0) == [[0, 3], [1], [2]]
+    assert get_indices(2.0) == [[0, 3, 1], [2]]
+    assert get_indices(4.0) == [[0, 3, 1, 2]]
+    assert get_indices(0.0, k=3) == [[0, 3], [1]]
+    with pytest.raises(ValueError):
+        get_indices(-1.0)
+
+
+def test_find_best() -> None:
+    fuzzy_search = FuzzySearch.from_config(FuzzyConfig(profiling=True))
+    candidates = ["xfoo", "foo", "bar", "foo"]
+    assert fuzzy_search.find_best("foo", candidates) == (1, 8.0, [0, 1, 2])
+    assert fuzzy_search.total_candidates_processed() == 4
+    assert fuzzy_search.find_best("fo", ["xfoo", "bar"]) == (0, 4.0, [1, 2])
+    assert fuzzy_search.find_best("baz", candidates) is None
+
+
+def test_find_best_command_palette() -> None:
+    fuzzy_search = FuzzySearch.for_command_palette()
+    candidates = ["gs", "git status"]
+    # Bonuses score "git status" higher than the exact match
+    assert fuzzy_search.find_best("gs", candidates) == (1, 13.125, [0, 4])
+    assert fuzzy_search.find_best("gs", candidates) == (
+        fuzzy_search.match_batch("gs", candidates)[0]
+    )
+
+
+def test_match_bytes() -> None:
+    fuzzy_search = FuzzySearch()
+    assert fuzzy_search.match_bytes("bar", b"foo bar") == (8.0, [4, 5, 6])
+    score, positions = fuzzy_search.match_bytes("bar", "café bar".encode())
+    assert score == fuzzy_search.match("bar", "café bar")[0]
+    assert positions == [6, 7, 8]
+    assert fuzzy_search.match_bytes("baz", b"foo bar")[0] == 0.0
+    with pytest.raises(ValueError, match="byte 3"):
+        fuzzy_search.match_bytes("foo", b"foo\xff")
+
+
+@pytest.mark.parametrize(
+    "query, candidate, token_coverage",
+    [("foo", "foo", 1.0), ("foo", "foo bar", 0.5), ("fb", "foo bar", 1.0)],
+)
+def test_token_coverage_boost(
+    query: str, candidate: str, token_coverage: float
+) -> None:
+    fuzzy_search = FuzzySearch.from_config(FuzzyConfig(token_coverage_boost=2.0))
+    breakdown = fuzzy_search.explain(query, candidate)
+    assert breakdown is not None
+    assert breakdown.token_coverage == token_coverage
+    assert fuzzy_search.match(query, candidate)[0] == pytest.approx(
+        FuzzySearch().match(query, candidate)[0] * (1 + 2.0 * token_coverage)
+    )
+
+
+def test_min_match_score() -> None:
+    fuzzy_search = FuzzySearch.from_config(FuzzyConfig(min_match_score=5.0))
+    assert fuzzy_search.match("foo", "foo") == (8.0, [0, 1, 2])
+    assert FuzzySearch().match("fb", "foobar") == (3.75, [0, 3])
+    assert fuzzy_search.match("fb", "foobar") == (5.0, [0, 3])
+    assert fuzzy_search.match("baz", "foobar")[0] == 0.0
+    assert fuzzy_search.count_above("fo", ["foo", "xfoo", "bar"], 4.9) == 2
+
+
+def test_match_mask() -> None:
+    candidates = ["foo", "bar", "xfoo", "foo"]
+    fuzzy_search = FuzzySearch()
+    assert fuzzy_search.match_mask("foo", candidates) == [True, False, True, True]
+    assert fuzzy_search.match_mask("foo", candidates, 6.0) == [True, False, False, True]
+    fuzzy_search = FuzzySearch.from_config(
+        FuzzyConfig(max_candidates=3, dedup_candidates=True)
+    )
+    assert fuzzy_search.match_mask("foo", candidates) == [True, False, True, False]
+
+
+def test_match_weighted_chars() -> None:
+    fuzzy_search = FuzzySearch()
+    assert fuzzy_search.match("foo", "foo foo") == (8.0, [0, 1, 2])
+    assert fuzzy_search.match_weighted_chars("foo", "foo foo", [0.0] * 7) == (
+        8.0,
+        [0, 1, 2],
+    )
+    weights = [0.0, 0.0, 0.0, 0.0, 1.0, 1.0, 1.0]
+    assert fuzzy_search.match_weighted_chars("foo", "foo foo", weights) == (
+        11.0,
+        [4, 5, 6],
+    )
+    assert fuzzy_search.match_weighted_chars("baz", "foo foo", weights)[0] == 0.0
+    with pytest.raises(ValueError):
+        fuzzy_search.match_weighted_chars("foo", "foo foo", [1.0])
+
+
+def test_max_combinations() -> None:
+    fuzzy_search = FuzzySearch()
+    assert fuzzy_search.match("bar", "b a bar") == (8.0, [4, 5, 6])
+    assert fuzzy_search.capped_matches() == []
+    fuzzy_search = FuzzySearch.from_config(FuzzyConfig(max_combinations=1))
+    assert fuzzy_search.match("bar", "b a bar")[1] == [0, 2, 6]
+    assert fuzzy_search.match("foo", "foo") == (8.0, [0, 1, 2])
+    assert fuzzy_search.capped_matches() == [("bar", "b a bar")]
+    fuzzy_search.clear_capped_matches()
+    assert fuzzy_search.capped_matches() == []
+
+
+def test_match_iter() -> None:
+    fuzzy_search = FuzzySearch()
+    candidates = ["xfoo", "bar", "foo", "f_o_o", "foo bar"]
+    assert fuzzy_search.match_iter("foo", iter(candidates), 2) == (
+        fuzzy_search.match_batch("foo", candidates, 2)
+    )
+    assert fuzzy_search.match_iter("foo", (c for c in candidates), 10) == (
+        fuzzy_search.match_batch("foo", candidates)
+    )
+    assert fuzzy_search.match_iter("foo", iter(candidates), 0) == []
+
+
+@pytest.mark.parametrize(
+    "candidate, default_first_letters, unicode_first_letters",
+    [
+        ("e\u0301cole e\u0301t", {0, 2, 7, 9}, {0, 7}),
+        ("日本語 テキスト", {0, 4}, {0, 1, 2, 4}),
+        ("na\u00efve caf\u00e9", {0, 6}, {0, 6}),
+    ],
+)
+def test_unicode_word_boundaries(
+    candidate: str,
+    default_first_letters: set[int],
+    unicode_first_letters: set[int],
+) -> None:
+    assert FuzzySearch().get_first_letters(candidate) == default_first_letters
+    fuzzy_search = FuzzySearch.from_config(FuzzyConfig(unicode_word_boundaries=True))
+    assert fuzzy_search.get_first_letters(candidate) == unicode_first_letters
+
+
+@pytest.mark.parametrize(
+    "config",
+    [
+        FuzzyConfig(),
+        FuzzyConfig(path_mode=True),
+        FuzzyConfig.command_palette(),
+        FuzzyConfig(length_penalty=0.1, token_coverage_boost=1.0),
+    ],
+)
+def test_prefix_fast_path(config: FuzzyConfig) -> None:
+    fuzzy_search = FuzzySearch.from_config(config)
+    fast_search = FuzzySearch.from_config(
+        replace(config, prefix_fast_path=True, profiling=True)
+    )
+    for query, candidate in [
+        ("foo", "foobar"),
+        ("foo", "foo foo"),
+        ("src", "src/foo.py"),
+        ("fb", "foobar"),
+        ("foo", "xfoo"),
+    ]:
+        assert fast_search.match(query, candidate) == (
+            fuzzy_search.match(query, candidate)
+        )
+    # Prefix matches skip enumerating alignments
+    _score, _positions, timings = fast_search.match_timed("foo", "foobar")
+    assert timings["enumeration"] == 0.0
+
+
+def test_match_for_ranker() -> None:
+    fuzzy_search = FuzzySearch()
+    assert fuzzy_search.match_for_ranker("foo", "foo") == (0.5, [0, 1, 2])
+    assert fuzzy_search.match_for_ranker("foo", "xfoo") == (3 / 7, [1, 2, 3])
+    assert fuzzy_search.match_for_ranker("foo", "foo bar") == (0.5, [0, 1, 2])
+    compiled_query = fuzzy_search.compile_query("foo")
+    assert fuzzy_search.match_for_ranker(compiled_query, "xfoo")[0] == 3 / 7
+    assert fuzzy_search.match_for_ranker("foo", "bar")[0] == 0.0
+
+
+def test_match_for_ranker_keeps_order_above_exact_match() -> None:
+    fuzzy_search = FuzzySearch.for_command_palette()
+    candidates = ["gs", "git status", "ages"]
+    scores = [fuzzy_search.match("gs", candidate)[0] for candidate in candidates]
+    ranks = [
+        fuzzy_search.match_for_ranker("gs", candidate)[0] for candidate in candidates
+    ]
+    # The first letter boost scores "git status" above an exact match
+    assert scores[1] > scores[0] > scores[2]
+    assert ranks[1] > ranks[0] > ranks[2]
+    assert all(0 < rank < 1 for rank in ranks)
+
+
+def test_shared_across_threads() -> None:
+    fuzzy_search = FuzzySearch.from_config(
+        FuzzyConfig(cache_size=16, profiling=True, max_combinations=1)
+    )
+    thread_count = 8
+    query_count = 3000
+    candidates = ["foo bar", "fob", "bar foo"]
+    expected = {
+        candidate: FuzzySearch.from_config(FuzzyConfig(max_combinations=1)).match(
+            "fo", candidate
+        )
+        for candidate in candidates
+    }
+    errors: list[BaseException] = []
+    barrier = threading.Barrier(thread_count)
+
+    def search(thread_index: int) -> None:
+        try:
+            barrier.wait()
+            for index in range(query_count):
+                candidate = candidates[index % len(candidates)]
+                # Distinct queries push past `MAX_RECENT_QUERIES`
+                fuzzy_search.match(f"{thread_index}-{index}", candidate)
+                assert fuzzy_search.match("fo", candidate) == expected[candidate]
+                fuzzy_search.recent_queries(limit=5)
+                fuzzy_search.capped_matches()
+        except BaseException as error:
+            errors.append(error)
+
+    threads = [
+        threading.Thread(target=search, args=(thread_index,))
+        for thread_index in range(thread_count)
+    ]
+    # Switch threads often, so races are likely even with the GIL
+    switch_interval = sys.getswitchinterval()
+    sys.setswitchinterval(1e-6)
+    try:
+        for thread in threads:
+            thread.start()
+        for thread in threads:
+            thread.join()
+    finally:
+        sys.setswitchinterval(switch_interval)
+    assert errors == []
+    assert len(fuzzy_search.cache) <= 16
+    assert len(fuzzy_search.recent_queries(limit=1000)) == (
+        FuzzySearch.MAX_RECENT_QUERIES
+    )
+    assert len(fuzzy_search.capped_matches()) <= FuzzySearch.MAX_CAPPED_MATCHES
+    assert fuzzy_search.total_candidates_processed() == (
+        thread_count * query_count * 2
+    )
+
+
+@pytest.mark.parametrize(
+    "candidate, first_letters",
+    [
+        ("/src/foo.py", {1, 5}),
+        ("src/foo.py", {0, 4}),
+        ("//src", {2}),
+        ("/", set()),
+    ],
+)
+def test_skip_leading_separator(candidate: str, first_letters: set[int]) -> None:
+    fuzzy_search = FuzzySearch.from_config(
+        FuzzyConfig(path_mode=True, skip_leading_separator=True)
+    )
+    assert fuzzy_search.get_first_letters(candidate) == first_letters
+    assert 0 in FuzzySearch(path_mode=True).get_first_letters(candidate)
+
+
+def test_match_metadata() -> None:
+    fuzzy_search = FuzzySearch()
+    assert fuzzy_search.match_metadata("fobr", ["bar", "foobar", "xfoo bar"]) == [
+        (1, 6.25, 0, 6, 3),
+        (2, 6.25, 1, 7, 3),
+    ]
+
+
+def test_serial_fallback(caplog: pytest.LogCaptureFixture) -> None:
+    candidate = "foo bar baz qux " * 8
+    fuzzy_search = FuzzySearch.from_config(
+        FuzzyConfig(parallel_chunk_size=8, thread_count=0)
+    )
+    assert fuzzy_search.match("bar", candidate) == FuzzySearch().match("bar", candidate)
+    assert "matching serially" in caplog.text
+
+
+def test_match_batch_pinned() -> None:
+    fuzzy_search = FuzzySearch()
+    candidates = ["foo", "xfoo", "bar", "f_o_o"]
+    pinned = [False, True, True, False]
+    results = fuzzy_search.match_batch_pinned("foo", candidates, pinned)
+    assert [index for index, _score, _positions in results] == [1, 0, 3]
+    results = fuzzy_search.match_batch_pinned("foo", candidates, pinned, k=2)
+    assert [index for index, _score, _positions in results] == [1, 0]
+    with pytest.raises(ValueError):
+        fuzzy_search.match_batch_pinned("foo", candidates, pinned[:2])
+
+
+@pytest.mark.parametrize(
+    "pattern, candidate, expected",
+    [
+        ("*.py", "foo.py", (4 / 7, [3, 4, 5])),
+        ("f?o.py", "foo.py", (6 / 7, [0, 2, 3, 4, 5])),
+        ("foo.py", "FOO.PY", (1.0, [0, 1, 2, 3, 4, 5])),
+        ("[a]", "[a]", (1.0, [0, 1, 2])),
+        ("*.py", "foo.pyc", (0.0, ())),
+        ("a.b", "axb", (0.0, ())),
+        ("", "foo", (0.0, ())),
+    ],
+)
+def test_match_glob(
+    pattern: str, candidate: str, expected: tuple[float, list[int]]
+) -> None:
+    score, positions = FuzzySearch().match_glob(pattern, candidate)
+    assert (score, positions) == (pytest.approx(expected[0]), expected[1])
@@ -0,0 +1,25 @@
+import pytest
+
+from toad._path_fuzzy_search import PathFuzzySearch
+from toad.fuzzy import FuzzyConfig
+
+
+def test_depth_penalty() -> None:
+    candidates = ["src/a/b/foo.py", "src/foo.py", "foo.py"]
+    no_penalty_search = PathFuzzySearch()
+    assert (
+        no_penalty_search.match("foo", candidates[0])[0]
+        == no_penalty_search.match("foo", candidates[2])[0]
+    )
+    fuzzy_search = PathFuzzySearch(depth_penalty=0.5)
+    scores = [fuzzy_search.match("foo", candidate)[0] for candidate in candidates]
+    assert scores[0] < scores[1] < scores[2]
+    assert fuzzy_search.match("foo", "src/foo.py")[0] == pytest.approx(16.0 / 1.5)
+
+
+def test_path_match_batch_with_empty_candidate() -> None:
+    fuzzy_search = PathFuzzySearch(
+        config=FuzzyConfig(path_mode=True, acronym_fallback=0.5)
+    )
+    results = fuzzy_search.match_batch("fb", ["", "foo/bar", "src/"])
+    assert [index for index, _score, _positions in results] == [1]
@@ -0,0 +1,28 @@
+from itertools import combinations, product
+
+from toad._path_match import PathFuzzySearch, match_path
+
+
+def test_repeated_characters_find_best_alignment() -> None:
+    fuzzy_search = PathFuzzySearch()
+    assert fuzzy_search.match("aa", "abaa") == (8.0, [2, 3])
+    assert match_path(("aa", "abaa")) == (8.0, (2, 3), "abaa")
+
+
+def test_match_is_exhaustive() -> None:
+    fuzzy_search = PathFuzzySearch()
+    for length in range(1, 6):
+        for candidate in map("".join, product("ab/", repeat=length)):
+            for query in ["a", "aa", "ab", "aba", "a/a"]:
+                best_score = max(
+                    (
+                        fuzzy_search.score(candidate, offsets)
+                        for offsets in combinations(range(len(candidate)), len(query))
+                        if all(
+                            candidate[offset] == character
+                            for offset, character in zip(offsets, query)
+                        )
+                    ),
+                    default=0.0,
+                )
+                assert fuzzy_search.match(query, candidate)[0] == best_score